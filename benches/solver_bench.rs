@@ -1,14 +1,15 @@
-use criterion::{Criterion, black_box, criterion_group, criterion_main};
-use yahtzee_solver::types::{JokerRule, ScorecardState};
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use yahtzee_solver::types::{JokerRule, RuleSet, ScorecardState};
 use yahtzee_solver::{MockScorecardMemo, dice_dp};
 
 fn bench_dice_dp(c: &mut Criterion) {
     let scorecard_state = ScorecardState::default();
     let scorecard_memo = MockScorecardMemo();
-    let joker_rule = JokerRule::FreeChoice;
+    let rule_set = RuleSet::standard(JokerRule::FreeChoice);
 
     c.bench_function("dice_dp_full_pass", |b| {
-        b.iter(|| black_box(dice_dp(&scorecard_state, &scorecard_memo, joker_rule)))
+        b.iter(|| black_box(dice_dp(&scorecard_state, &scorecard_memo, &rule_set)))
     });
 }
 
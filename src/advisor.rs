@@ -0,0 +1,367 @@
+use crate::risk::{RiskObjective, RiskSolver};
+use crate::types::{Action, DiceState, RollCounts, RuleSet, ScoreCategory, ScorecardState};
+use crate::{DpValue, MapMemo, Memo, VecMemo, dice_dp, dice_state_index};
+
+/// Answers "given this scorecard, these dice and this many rolls left, what should I do?" from a
+/// solved scorecard table. Rather than storing the policy of every scorecard state, we re-solve
+/// the dice DP of each queried scorecard state, and cache it since a turn queries the same
+/// scorecard state up to three times.
+pub struct Advisor<S: Memo<ScorecardState, f64>> {
+    scorecard_memo: S,
+    rule_set: RuleSet,
+    turn_memo: MapMemo<ScorecardState, TurnSolution>,
+    risk_solvers: Vec<RiskSolver>,
+}
+
+/// The optimal action from a decision point, and the EV of the rest of the game when following
+/// it. The EV does not include points already scored.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Advice {
+    pub action: Action,
+    pub ev: f64,
+}
+
+/// A legal action from a decision point, with the EV of the rest of the game when taking it (and
+/// playing optimally afterwards), and how much EV is lost against the best action.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RankedAction {
+    pub action: Action,
+    pub ev: f64,
+    pub loss: f64,
+}
+
+/// Which policy an advisor query follows.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Policy {
+    /// Maximise the EV, using the scorecard table.
+    MaxEv,
+    /// Maximise a risk-sensitive objective. This is solved on demand rather than from the table.
+    Risk(RiskObjective),
+}
+
+/// The solved dice DP of a single scorecard state.
+pub(crate) struct TurnSolution<V = f64, A = Action> {
+    pub(crate) ev_memo: VecMemo<DiceState, V>,
+    pub(crate) policy_memo: VecMemo<DiceState, A>,
+}
+
+impl<S: Memo<ScorecardState, f64>> Advisor<S> {
+    /// * `scorecard_memo` - The solved ScorecardState -> EV table, for example a loaded
+    ///   `ScorecardTable`.
+    /// * `rule_set` - The rules the table was solved with.
+    pub fn new(scorecard_memo: S, rule_set: RuleSet) -> Self {
+        Self {
+            scorecard_memo,
+            rule_set,
+            turn_memo: MapMemo::default(),
+            risk_solvers: Vec::new(),
+        }
+    }
+
+    /// The optimal action from the given decision point, or None if the game is over. The
+    /// scorecard state must be reachable, as the table only holds reachable states.
+    ///
+    /// * `scorecard_state` - The current scorecard.
+    /// * `dice_state` - The current dice and the number of rerolls left this turn.
+    pub fn advise(
+        &mut self,
+        scorecard_state: &ScorecardState,
+        dice_state: &DiceState,
+    ) -> Option<Advice> {
+        if scorecard_state.is_terminal() {
+            return None;
+        }
+        let turn_solution = self.turn_solution(scorecard_state);
+        Some(Advice {
+            action: *turn_solution
+                .policy_memo
+                .get(dice_state)
+                .expect("dice_dp has an action for every dice state of a non-terminal state."),
+            ev: *turn_solution
+                .ev_memo
+                .get(dice_state)
+                .expect("dice_dp fills in every dice state."),
+        })
+    }
+
+    /// The same as advise, but following the given policy. The `ev` of the advice is always the
+    /// mean of the rest of the game when following that policy.
+    ///
+    /// * `scorecard_state` - The current scorecard.
+    /// * `dice_state` - The current dice and the number of rerolls left this turn.
+    /// * `policy` - The policy to follow.
+    pub fn advise_with(
+        &mut self,
+        scorecard_state: &ScorecardState,
+        dice_state: &DiceState,
+        policy: Policy,
+    ) -> Option<Advice> {
+        match policy {
+            Policy::MaxEv => self.advise(scorecard_state, dice_state),
+            Policy::Risk(objective) => self
+                .risk_solver(objective)
+                .advise(scorecard_state, dice_state),
+        }
+    }
+
+    /// Every legal action from the given decision point, best first, or an empty list if the game
+    /// is over. Keeps are only legal with rerolls left. The scorecard state must be reachable.
+    ///
+    /// * `scorecard_state` - The current scorecard.
+    /// * `dice_state` - The current dice and the number of rerolls left this turn.
+    pub fn rank_actions(
+        &mut self,
+        scorecard_state: &ScorecardState,
+        dice_state: &DiceState,
+    ) -> Vec<RankedAction> {
+        if scorecard_state.is_terminal() {
+            return Vec::new();
+        }
+        let mut actions: Vec<(Action, f64)> = Vec::new();
+        let roll_counts = &dice_state.roll_counts;
+        for score_category in scorecard_state.valid_score_categories(roll_counts, &self.rule_set) {
+            let ev = self.score_ev(scorecard_state, roll_counts, score_category);
+            actions.push((Action::Score(score_category), ev));
+        }
+        let rolls_left = *dice_state.rolls_left.rolls_left();
+        if rolls_left > 0 {
+            let tables = self.rule_set.dice.tables();
+            let turn_solution = self.turn_solution(scorecard_state);
+            for &keep_counts in tables.valid_keep_counts(roll_counts) {
+                let ev = f64::expectation(tables.transitions().get(&keep_counts).iter().map(
+                    |&(target_roll_counts_rank, p)| {
+                        let memo_idx = dice_state_index(
+                            roll_counts.dice(),
+                            target_roll_counts_rank,
+                            rolls_left - 1,
+                        );
+                        (
+                            p,
+                            turn_solution
+                                .ev_memo
+                                .raw_get(memo_idx)
+                                .expect("dice_dp fills in every dice state."),
+                        )
+                    },
+                ));
+                actions.push((Action::Keep(keep_counts), ev));
+            }
+        }
+        // stable, so ties keep scoring before keeping, as in dice_dp.
+        actions.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        let best_ev = actions[0].1;
+        actions
+            .into_iter()
+            .map(|(action, ev)| RankedAction {
+                action,
+                ev,
+                loss: best_ev - ev,
+            })
+            .collect()
+    }
+
+    /// Drops every cached dice DP, and every solved risk-sensitive policy.
+    pub fn clear_cache(&mut self) {
+        self.turn_memo = MapMemo::default();
+        self.risk_solvers.clear();
+    }
+
+    /// The solver of the objective, creating it first if needed.
+    fn risk_solver(&mut self, objective: RiskObjective) -> &mut RiskSolver {
+        let index = match self
+            .risk_solvers
+            .iter()
+            .position(|x| x.objective() == objective)
+        {
+            Some(index) => index,
+            None => {
+                self.risk_solvers
+                    .push(RiskSolver::new(self.rule_set, objective));
+                self.risk_solvers.len() - 1
+            }
+        };
+        &mut self.risk_solvers[index]
+    }
+
+    /// The cached dice DP of the scorecard state, solving it first if needed.
+    fn turn_solution(&mut self, scorecard_state: &ScorecardState) -> &TurnSolution {
+        if self.turn_memo.get(scorecard_state).is_none() {
+            let turn_solution = self.solve_turn(scorecard_state);
+            self.turn_memo.set(*scorecard_state, turn_solution);
+        }
+        self.turn_memo.get(scorecard_state).unwrap()
+    }
+
+    fn solve_turn(&self, scorecard_state: &ScorecardState) -> TurnSolution {
+        let (ev_memo, policy_memo) = dice_dp(scorecard_state, &self.scorecard_memo, &self.rule_set);
+        TurnSolution {
+            ev_memo,
+            policy_memo,
+        }
+    }
+
+    /// The immediate score (with bonuses) of scoring the roll in the category, plus the EV of the
+    /// resulting scorecard state.
+    fn score_ev(
+        &self,
+        scorecard_state: &ScorecardState,
+        roll_counts: &RollCounts,
+        score_category: ScoreCategory,
+    ) -> f64 {
+        let (category_score, bonus_score) = scorecard_state
+            .score_value(roll_counts, score_category, &self.rule_set)
+            .expect("We are iterating through valid categories.");
+        let target_scorecard_state = scorecard_state
+            .score(score_category, category_score)
+            .expect("This is a valid score category.");
+        let transition_ev = if !target_scorecard_state.is_terminal() {
+            self.scorecard_memo
+                .get(&target_scorecard_state)
+                .copied()
+                .expect("The scorecard table holds every reachable state.")
+        } else {
+            0f64
+        };
+        (category_score + bonus_score) as f64 + transition_ev
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{JokerRule, KeepCounts, RollsLeft};
+    use crate::{all_filled_except, scorecard_state_ev};
+
+    /// Only chance is left, so the table is never consulted.
+    fn chance_only_advisor() -> (Advisor<VecMemo<ScorecardState, f64>>, ScorecardState) {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let scorecard_state = all_filled_except(&rule_set, &[ScoreCategory::Chance]);
+        (Advisor::new(VecMemo::new(), rule_set), scorecard_state)
+    }
+
+    fn dice_state(roll_counts: [u8; RollCounts::MAX_FACES], rolls_left: u8) -> DiceState {
+        DiceState {
+            roll_counts: RollCounts::try_from(roll_counts).unwrap(),
+            rolls_left: RollsLeft::try_from(rolls_left).unwrap(),
+        }
+    }
+
+    #[test]
+    fn advise_score_high_roll() {
+        let (mut advisor, scorecard_state) = chance_only_advisor();
+        let advice = advisor
+            .advise(&scorecard_state, &dice_state([0, 0, 0, 0, 0, 5], 2))
+            .unwrap();
+        assert_eq!(advice.action, Action::Score(ScoreCategory::Chance));
+        assert_eq!(advice.ev, 30.0);
+    }
+
+    #[test]
+    fn advise_reroll_low_dice() {
+        let (mut advisor, scorecard_state) = chance_only_advisor();
+        let advice = advisor
+            .advise(&scorecard_state, &dice_state([1, 1, 1, 1, 1, 0], 1))
+            .unwrap();
+        // keep the 4 and 5 and reroll three dice for 3.5 each.
+        assert_eq!(
+            advice.action,
+            Action::Keep(KeepCounts::try_from([0, 0, 0, 1, 1, 0]).unwrap())
+        );
+        assert!((advice.ev - 19.5).abs() < 1e-9, "Got EV {}.", advice.ev);
+        let advice = advisor
+            .advise(&scorecard_state, &dice_state([1, 1, 1, 1, 1, 0], 0))
+            .unwrap();
+        assert_eq!(advice.action, Action::Score(ScoreCategory::Chance));
+        assert_eq!(advice.ev, 15.0);
+    }
+
+    #[test]
+    fn rank_actions_matches_advice() {
+        let (mut advisor, scorecard_state) = chance_only_advisor();
+        let dice_state = dice_state([1, 1, 1, 1, 1, 0], 1);
+        let ranked_actions = advisor.rank_actions(&scorecard_state, &dice_state);
+        // one category, plus every subset of five distinct dice.
+        assert_eq!(ranked_actions.len(), 1 + 32);
+        let advice = advisor.advise(&scorecard_state, &dice_state).unwrap();
+        assert_eq!(ranked_actions[0].action, advice.action);
+        assert_eq!(ranked_actions[0].ev, advice.ev);
+        assert_eq!(ranked_actions[0].loss, 0.0);
+        for pair in ranked_actions.windows(2) {
+            assert!(pair[0].ev >= pair[1].ev);
+        }
+        // keeping all of 1-2-3-4-5 is the same as scoring chance now.
+        let keep_all = ranked_actions
+            .iter()
+            .find(|x| x.action == Action::Keep(KeepCounts::try_from([1, 1, 1, 1, 1, 0]).unwrap()))
+            .unwrap();
+        assert!((keep_all.ev - 15.0).abs() < 1e-9);
+        assert!((keep_all.loss - 4.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rank_actions_no_rolls_left() {
+        let (mut advisor, scorecard_state) = chance_only_advisor();
+        let ranked_actions =
+            advisor.rank_actions(&scorecard_state, &dice_state([1, 1, 1, 1, 1, 0], 0));
+        assert_eq!(
+            ranked_actions,
+            vec![RankedAction {
+                action: Action::Score(ScoreCategory::Chance),
+                ev: 15.0,
+                loss: 0.0
+            }]
+        );
+    }
+
+    #[test]
+    fn advise_with_risk_policy() {
+        let (mut advisor, scorecard_state) = chance_only_advisor();
+        let dice_state = dice_state([1, 1, 1, 1, 1, 0], 1);
+        assert_eq!(
+            advisor.advise_with(&scorecard_state, &dice_state, Policy::MaxEv),
+            advisor.advise(&scorecard_state, &dice_state)
+        );
+        let risk_averse = Policy::Risk(RiskObjective::MeanVariance { lambda: 1.0 });
+        let advice = advisor
+            .advise_with(&scorecard_state, &dice_state, risk_averse)
+            .unwrap();
+        assert_eq!(advice.action, Action::Score(ScoreCategory::Chance));
+        assert_eq!(advice.ev, 15.0);
+    }
+
+    #[test]
+    fn advise_terminal() {
+        let (mut advisor, scorecard_state) = chance_only_advisor();
+        let scorecard_state = scorecard_state.score(ScoreCategory::Chance, 5).unwrap();
+        let advice = advisor.advise(&scorecard_state, &dice_state([5, 0, 0, 0, 0, 0], 0));
+        assert_eq!(advice, None);
+    }
+
+    #[test]
+    fn advise_yacht() {
+        let rule_set = RuleSet::yacht();
+        let scorecard_state = all_filled_except(
+            &rule_set,
+            &[ScoreCategory::FourOfAKind, ScoreCategory::Chance],
+        );
+        let mut memo = VecMemo::new();
+        for score_category in [ScoreCategory::FourOfAKind, ScoreCategory::Chance] {
+            let one_left = scorecard_state.score(score_category, 0).unwrap();
+            memo.set(one_left, scorecard_state_ev(&one_left, &memo, &rule_set));
+        }
+        let chance_ev = *memo
+            .get(
+                &scorecard_state
+                    .score(ScoreCategory::FourOfAKind, 0)
+                    .unwrap(),
+            )
+            .unwrap();
+        let mut advisor = Advisor::new(memo, rule_set);
+        // four of a kind counts only the four sixes, which still beats using up chance.
+        let advice = advisor
+            .advise(&scorecard_state, &dice_state([0, 0, 0, 1, 0, 4], 0))
+            .unwrap();
+        assert_eq!(advice.action, Action::Score(ScoreCategory::FourOfAKind));
+        assert!((advice.ev - (24.0 + chance_ev)).abs() < 1e-9);
+    }
+}
@@ -1,37 +1,237 @@
-use crate::types::{KeepCounts, RollCounts, SubtractionError};
-
-pub const DISTINCT_ROLL_COUNTS: usize =
-    BINOM[RollCounts::NUM_DICE + RollCounts::NUM_FACES - 1][RollCounts::NUM_FACES - 1];
-pub const DISTINCT_YAHTZEE_ROLL_COUNTS: usize = RollCounts::NUM_FACES;
-pub const DISTINCT_NON_YAHTZEE_ROLL_COUNTS: usize =
-    DISTINCT_ROLL_COUNTS - DISTINCT_YAHTZEE_ROLL_COUNTS;
-pub const DISTINCT_ROLLS: [[u8; RollCounts::NUM_FACES]; DISTINCT_ROLL_COUNTS] =
-    make_distinct_rolls();
-pub const DISTINCT_NON_YAHTZEE_ROLLS: [[u8; RollCounts::NUM_FACES];
-    DISTINCT_NON_YAHTZEE_ROLL_COUNTS] = make_distinct_non_yahtzee_rolls();
-pub const DISTINCT_YAHTZEE_ROLLS: [[u8; RollCounts::NUM_FACES]; DISTINCT_YAHTZEE_ROLL_COUNTS] =
-    make_distinct_yahtzee_rolls();
-pub const DISTINCT_KEEP_COUNTS: usize = compute_distinct_keep_counts();
-pub const DISTINCT_KEEPS: [[u8; RollCounts::NUM_FACES]; DISTINCT_KEEP_COUNTS] =
-    make_distinct_keeps();
-
-const MAX: usize = RollCounts::NUM_DICE + RollCounts::NUM_FACES;
-
-/// Precomputed Pascal's triangle with 11 rows.
+use crate::DpProbability;
+use crate::types::{Dice, KeepCounts, RollCounts};
+use std::sync::OnceLock;
+
+/// The most distinct rolls of any dice, for sizing memos indexed by roll rank.
+pub const MAX_DISTINCT_ROLL_COUNTS: usize =
+    BINOM[Dice::MAX_DICE as usize + RollCounts::MAX_FACES - 1][RollCounts::MAX_FACES - 1];
+
+const MAX: usize = Dice::MAX_DICE as usize + RollCounts::MAX_FACES + 1;
+
+/// Precomputed Pascal's triangle, large enough for any dice.
 const BINOM: [[usize; MAX]; MAX] = make_binom();
+
+/// The tables of each dice, indexed by [num_dice - 1][num_faces - 1] and built on first use.
+static DICE_TABLES: [[OnceLock<DiceTables>; RollCounts::MAX_FACES]; Dice::MAX_DICE as usize] =
+    [const { [const { OnceLock::new() }; RollCounts::MAX_FACES] }; Dice::MAX_DICE as usize];
+
+/// Everything about rolling a set of dice that the DP needs, enumerated once per dice.
+pub(crate) struct DiceTables {
+    /// Every distinct roll, in rank order.
+    rolls: Vec<RollCounts>,
+    /// Every distinct keep, in rank order.
+    keeps: Vec<KeepCounts>,
+    /// The valid keeps of each roll, indexed by roll rank.
+    valid_keep_counts: Vec<Vec<KeepCounts>>,
+    transitions: RollTransitions,
+    /// The same as transitions, with exact probabilities. Only the exact DP needs these, so they
+    /// are built on first use.
+    #[cfg(feature = "exact")]
+    exact_transitions: OnceLock<RollTransitions<num_rational::BigRational>>,
+}
+
+/// The roll_probabilities of every keep, stored back to back in one allocation (CSR style) and
+/// indexed by keep rank.
+pub(crate) struct RollTransitions<P = f64> {
+    /// The outcomes of the keep with rank r are outcomes[row_starts[r]..row_starts[r + 1]].
+    row_starts: Vec<usize>,
+    outcomes: Vec<(usize, P)>,
+}
+
+impl DpProbability for f64 {
+    fn transition_row(dice: &Dice, keep_rank: usize) -> &'static [(usize, Self)] {
+        dice.tables().transitions.row(keep_rank)
+    }
+
+    fn p_roll(roll_counts: &RollCounts) -> Self {
+        roll_counts.p_roll()
+    }
+}
+
+#[cfg(feature = "exact")]
+impl DpProbability for num_rational::BigRational {
+    fn transition_row(dice: &Dice, keep_rank: usize) -> &'static [(usize, Self)] {
+        let tables = dice.tables();
+        tables
+            .exact_transitions
+            .get_or_init(|| tables.transitions.to_exact(&tables.rolls, &tables.keeps))
+            .row(keep_rank)
+    }
+
+    fn p_roll(roll_counts: &RollCounts) -> Self {
+        roll_counts.p_roll_given_keep_exact(&KeepCounts::none(*roll_counts.dice()))
+    }
+}
+
+impl Dice {
+    /// The number of distinct rolls, as multisets of faces.
+    pub fn num_rolls(&self) -> usize {
+        let (num_dice, num_faces) = (self.num_dice() as usize, self.num_faces() as usize);
+        BINOM[num_dice + num_faces - 1][num_faces - 1]
+    }
+
+    /// The number of distinct keeps, as multisets of faces with at most num_dice dice.
+    pub fn num_keeps(&self) -> usize {
+        let (num_dice, num_faces) = (self.num_dice() as usize, self.num_faces() as usize);
+        // keeping n dice is rolling n dice of num_faces + 1 faces, where the extra face is "not
+        // kept".
+        BINOM[num_dice + num_faces][num_faces]
+    }
+
+    /// Every distinct roll of these dice, in rank order.
+    pub fn rolls(&self) -> &'static [RollCounts] {
+        &self.tables().rolls
+    }
+
+    /// Every distinct keep of these dice, in rank order.
+    pub fn keeps(&self) -> &'static [KeepCounts] {
+        &self.tables().keeps
+    }
+
+    pub(crate) fn tables(&self) -> &'static DiceTables {
+        DICE_TABLES[self.num_dice() as usize - 1][self.num_faces() as usize - 1]
+            .get_or_init(|| DiceTables::new(*self))
+    }
+}
+
+impl DiceTables {
+    fn new(dice: Dice) -> Self {
+        let rolls: Vec<RollCounts> = enumerate_counts(&dice, true)
+            .into_iter()
+            .map(|x| RollCounts::new(x, dice).unwrap())
+            .collect();
+        let keeps: Vec<KeepCounts> = enumerate_counts(&dice, false)
+            .into_iter()
+            .map(|x| KeepCounts::new(x, dice).unwrap())
+            .collect();
+        let valid_keep_counts = rolls.iter().map(|x| x.valid_keep_counts()).collect();
+        let transitions = RollTransitions::new(&rolls, &keeps);
+        Self {
+            rolls,
+            keeps,
+            valid_keep_counts,
+            transitions,
+            #[cfg(feature = "exact")]
+            exact_transitions: OnceLock::new(),
+        }
+    }
+
+    pub(crate) fn rolls(&self) -> &[RollCounts] {
+        &self.rolls
+    }
+
+    /// The keeps of a roll, as given by valid_keep_counts.
+    pub(crate) fn valid_keep_counts(&self, roll_counts: &RollCounts) -> &[KeepCounts] {
+        &self.valid_keep_counts[roll_counts.rank()]
+    }
+
+    pub(crate) fn transitions(&self) -> &RollTransitions {
+        &self.transitions
+    }
+}
+
+impl RollTransitions {
+    fn new(rolls: &[RollCounts], keeps: &[KeepCounts]) -> Self {
+        let mut row_starts = vec![0];
+        let mut outcomes = Vec::new();
+        for keep_counts in keeps {
+            outcomes.extend(keep_counts.roll_probabilities(rolls));
+            row_starts.push(outcomes.len());
+        }
+        Self {
+            row_starts,
+            outcomes,
+        }
+    }
+
+    /// The same outcomes, with exact probabilities.
+    ///
+    /// * `rolls` - Every distinct roll of the dice.
+    /// * `keeps` - Every distinct keep of the dice.
+    #[cfg(feature = "exact")]
+    fn to_exact(
+        &self,
+        rolls: &[RollCounts],
+        keeps: &[KeepCounts],
+    ) -> RollTransitions<num_rational::BigRational> {
+        let mut outcomes = Vec::with_capacity(self.outcomes.len());
+        for (keep_rank, keep_counts) in keeps.iter().enumerate() {
+            for &(target_roll_counts_rank, _) in self.row(keep_rank) {
+                outcomes.push((
+                    target_roll_counts_rank,
+                    rolls[target_roll_counts_rank].p_roll_given_keep_exact(keep_counts),
+                ));
+            }
+        }
+        RollTransitions {
+            row_starts: self.row_starts.clone(),
+            outcomes,
+        }
+    }
+}
+
+impl<P> RollTransitions<P> {
+    /// The (target rollcount rank, probability) of every rollcount reachable from the keep with
+    /// the given rank.
+    pub(crate) fn row(&self, keep_rank: usize) -> &[(usize, P)] {
+        &self.outcomes[self.row_starts[keep_rank]..self.row_starts[keep_rank + 1]]
+    }
+
+    /// The same as row, but looked up by the keep itself.
+    pub(crate) fn get(&self, keep_counts: &KeepCounts) -> &[(usize, P)] {
+        self.row(keep_counts.rank())
+    }
+}
+
+impl KeepCounts {
+    /// Given a multiset of kept dice, computes its position in Dice::keeps. A bijection between
+    /// KeepCounts <-> [0..Dice::num_keeps()].
+    pub fn rank(&self) -> usize {
+        let num_faces = self.dice().num_faces() as usize;
+        let mut rank: usize = 0;
+        let mut dice_remaining = self.dice().num_dice() as usize;
+        for face in 0..num_faces {
+            let count = self.keep_counts()[face] as usize;
+            let faces_left = num_faces - face - 1;
+            // every keep with fewer of this face comes first, and there are C(n + k, k) ways to
+            // keep at most n dice of the k faces left.
+            for i in 0..count {
+                rank += BINOM[dice_remaining - i + faces_left][faces_left];
+            }
+            dice_remaining -= count;
+        }
+        rank
+    }
+
+    /// For these dice kept, the rank of every rollcount reachable by rerolling the rest and its
+    /// probability. Unreachable rollcounts are left out.
+    ///
+    /// * `rolls` - Every distinct roll of the dice.
+    fn roll_probabilities(&self, rolls: &[RollCounts]) -> Vec<(usize, f64)> {
+        let mut vec: Vec<(usize, f64)> = Vec::new();
+        for target_roll_counts in rolls {
+            let p = target_roll_counts.p_roll_given_keep(self);
+            if p > 0f64 {
+                vec.push((target_roll_counts.rank(), p));
+            }
+        }
+        vec
+    }
+}
+
 impl RollCounts {
     /// Given a dice roll as a multiset, computes the rank of the multiset. A bijection between
-    /// RollCounts <-> [0..C(10)(5)].
-    ///
-    /// * `roll_counts` - The dice roll multiset.
+    /// RollCounts <-> [0..Dice::num_rolls()].
     pub fn rank(&self) -> usize {
+        let num_faces = self.dice().num_faces() as usize;
         let mut rank: usize = 0;
-        let mut dice_remaining: usize = RollCounts::NUM_DICE as usize;
-        for face in 0..(RollCounts::NUM_DICE as usize) {
+        let mut dice_remaining = self.dice().num_dice() as usize;
+        // the count of the last face is whatever is left over.
+        for face in 0..num_faces - 1 {
             let count = self.roll_counts()[face] as usize;
+            let faces_left = num_faces - face - 1;
             for i in 0..count {
                 let dice_left = dice_remaining - i;
-                let faces_left = RollCounts::NUM_FACES as usize - face - 1;
                 rank += BINOM[dice_left + faces_left - 1][faces_left - 1];
             }
             dice_remaining -= count;
@@ -39,45 +239,101 @@ impl RollCounts {
         rank
     }
 
+    /// Every submultiset of this roll, in lexicographic order of the counts.
     pub fn valid_keep_counts(&self) -> Vec<KeepCounts> {
         let mut valid_keep_counts = Vec::new();
-        for k0 in 0..=self.roll_counts()[0] {
-            for k1 in 0..=self.roll_counts()[1] {
-                for k2 in 0..=self.roll_counts()[2] {
-                    for k3 in 0..=self.roll_counts()[3] {
-                        for k4 in 0..=self.roll_counts()[4] {
-                            for k5 in 0..=self.roll_counts()[5] {
-                                valid_keep_counts
-                                    .push(KeepCounts::try_from([k0, k1, k2, k3, k4, k5]).expect(
-                                            "Must be valid because it's a submultiset of a valid roll_counts.",
-                                    ));
-                            }
-                        }
-                    }
-                }
-            }
+        let mut keep = [0u8; RollCounts::MAX_FACES];
+        loop {
+            valid_keep_counts.push(
+                KeepCounts::new(keep, *self.dice())
+                    .expect("Must be valid because it's a submultiset of a valid roll_counts."),
+            );
+            // count up like an odometer, where each face's digit wraps after its count in the
+            // roll.
+            let Some(face) = (0..RollCounts::MAX_FACES)
+                .rev()
+                .find(|&face| keep[face] < self.roll_counts()[face])
+            else {
+                break;
+            };
+            keep[face] += 1;
+            keep[face + 1..].fill(0);
         }
         valid_keep_counts
     }
 
     pub fn p_roll(&self) -> f64 {
-        self.p_roll_given_keep(&KeepCounts::try_from([0, 0, 0, 0, 0, 0]).unwrap())
+        self.p_roll_given_keep(&KeepCounts::none(*self.dice()))
     }
 
     pub fn p_roll_given_keep(&self, keep_counts: &KeepCounts) -> f64 {
+        let (numerator, denominator) = self.p_roll_given_keep_fraction(keep_counts);
+        numerator as f64 / denominator as f64
+    }
+
+    /// The same as p_roll_given_keep, but exact.
+    #[cfg(feature = "exact")]
+    pub fn p_roll_given_keep_exact(&self, keep_counts: &KeepCounts) -> num_rational::BigRational {
+        let (numerator, denominator) = self.p_roll_given_keep_fraction(keep_counts);
+        num_rational::BigRational::new(numerator.into(), denominator.into())
+    }
+
+    /// p_roll_given_keep as (numerator, denominator).
+    fn p_roll_given_keep_fraction(&self, keep_counts: &KeepCounts) -> (usize, usize) {
         let Ok(to_reroll) = self.subtract(keep_counts) else {
-            return 0f64;
+            return (0, 1);
         };
         let n_to_reroll = to_reroll.keep_counts().iter().sum::<u8>() as usize;
         let mut denominator: usize = 1;
         for &count in to_reroll.keep_counts().iter() {
             denominator *= factorial(count as usize);
         }
-        denominator *= (RollCounts::NUM_FACES as usize).pow(n_to_reroll as u32);
+        denominator *= (self.dice().num_faces() as usize).pow(n_to_reroll as u32);
         let numerator = factorial(n_to_reroll);
-        let p = numerator as f64 / denominator as f64;
-        p
+        (numerator, denominator)
+    }
+}
+
+/// Every way of putting the dice's dice among its faces, in lexicographic order. If `exact`, every
+/// die is used (a roll), and otherwise at most every die is (a keep).
+fn enumerate_counts(dice: &Dice, exact: bool) -> Vec<[u8; RollCounts::MAX_FACES]> {
+    fn extend(
+        all_counts: &mut Vec<[u8; RollCounts::MAX_FACES]>,
+        counts: &mut [u8; RollCounts::MAX_FACES],
+        face: usize,
+        num_faces: usize,
+        dice_left: u8,
+        exact: bool,
+    ) {
+        if face == num_faces {
+            if !exact || dice_left == 0 {
+                all_counts.push(*counts);
+            }
+            return;
+        }
+        for count in 0..=dice_left {
+            counts[face] = count;
+            extend(
+                all_counts,
+                counts,
+                face + 1,
+                num_faces,
+                dice_left - count,
+                exact,
+            );
+        }
+        counts[face] = 0;
     }
+    let mut all_counts = Vec::new();
+    extend(
+        &mut all_counts,
+        &mut [0; RollCounts::MAX_FACES],
+        0,
+        dice.num_faces() as usize,
+        dice.num_dice(),
+        exact,
+    );
+    all_counts
 }
 
 const fn factorial(n: usize) -> usize {
@@ -106,147 +362,111 @@ const fn make_binom() -> [[usize; MAX]; MAX] {
     binom
 }
 
-const fn compute_distinct_keep_counts() -> usize {
-    let mut counts = 0usize;
-    let mut n_kept = 0usize;
-    while n_kept <= RollCounts::NUM_DICE {
-        counts += BINOM[n_kept + RollCounts::NUM_FACES - 1][n_kept];
-        n_kept += 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The standard dice, Maxi Yatzy's six dice, and five four-sided dice.
+    fn all_dice() -> [Dice; 3] {
+        [Dice::STANDARD, Dice::MAXI, Dice::new(5, 4).unwrap()]
     }
-    counts
-}
 
-/// We cannot return checked RollCount objects as that's not const.
-const fn make_distinct_rolls() -> [[u8; RollCounts::NUM_FACES]; DISTINCT_ROLL_COUNTS] {
-    let mut distinct_rolls = [[0, 0, 0, 0, 0, 0]; DISTINCT_ROLL_COUNTS];
-    let mut i = 0;
-    let mut c0 = 0u8;
-    while c0 <= RollCounts::NUM_DICE as u8 {
-        let mut c1 = 0u8;
-        while c0 + c1 <= RollCounts::NUM_DICE as u8 {
-            let mut c2 = 0u8;
-            while c0 + c1 + c2 <= RollCounts::NUM_DICE as u8 {
-                let mut c3 = 0u8;
-                while c0 + c1 + c2 + c3 <= RollCounts::NUM_DICE as u8 {
-                    let mut c4 = 0u8;
-                    while c0 + c1 + c2 + c3 + c4 <= RollCounts::NUM_DICE as u8 {
-                        let c5 = RollCounts::NUM_DICE as u8 - c0 - c1 - c2 - c3 - c4;
-                        distinct_rolls[i] = [c0, c1, c2, c3, c4, c5];
-                        i += 1;
-                        c4 += 1;
-                    }
-                    c3 += 1;
-                }
-                c2 += 1;
+    #[test]
+    fn rank_is_bijection() {
+        for dice in all_dice() {
+            let rolls = dice.rolls();
+            assert_eq!(rolls.len(), dice.num_rolls());
+            for (i, roll_counts) in rolls.iter().enumerate() {
+                assert_eq!(roll_counts.rank(), i, "{roll_counts:?}");
+            }
+            // every roll is distinct, so rank is also injective.
+            for pair in rolls.windows(2) {
+                assert!(pair[0].roll_counts() < pair[1].roll_counts(), "{pair:?}");
             }
-            c1 += 1;
         }
-        c0 += 1;
+        assert_eq!(Dice::STANDARD.num_rolls(), 252);
+        assert_eq!(Dice::MAXI.num_rolls(), 462);
+        assert_eq!(
+            Dice::new(Dice::MAX_DICE, 6).unwrap().num_rolls(),
+            MAX_DISTINCT_ROLL_COUNTS
+        );
     }
-    distinct_rolls
-}
 
-const fn make_distinct_keeps() -> [[u8; RollCounts::NUM_FACES]; DISTINCT_KEEP_COUNTS] {
-    let mut distinct_keeps = [[0, 0, 0, 0, 0, 0]; DISTINCT_KEEP_COUNTS];
-    let mut i = 0;
-    let mut c0 = 0u8;
-    while c0 <= RollCounts::NUM_DICE as u8 {
-        let mut c1 = 0u8;
-        while c0 + c1 <= RollCounts::NUM_DICE as u8 {
-            let mut c2 = 0u8;
-            while c0 + c1 + c2 <= RollCounts::NUM_DICE as u8 {
-                let mut c3 = 0u8;
-                while c0 + c1 + c2 + c3 <= RollCounts::NUM_DICE as u8 {
-                    let mut c4 = 0u8;
-                    while c0 + c1 + c2 + c3 + c4 <= RollCounts::NUM_DICE as u8 {
-                        let mut c5 = 0u8;
-                        while c0 + c1 + c2 + c3 + c4 + c5 <= RollCounts::NUM_DICE as u8 {
-                            distinct_keeps[i] = [c0, c1, c2, c3, c4, c5];
-                            c5 += 1;
-                            i += 1;
-                        }
-                        c4 += 1;
-                    }
-                    c3 += 1;
-                }
-                c2 += 1;
+    #[test]
+    fn keep_rank_matches_distinct_keeps() {
+        for dice in all_dice() {
+            let keeps = dice.keeps();
+            assert_eq!(keeps.len(), dice.num_keeps());
+            for (i, keep_counts) in keeps.iter().enumerate() {
+                assert_eq!(keep_counts.rank(), i, "{keep_counts:?}");
+            }
+            for pair in keeps.windows(2) {
+                assert!(pair[0].keep_counts() < pair[1].keep_counts(), "{pair:?}");
             }
-            c1 += 1;
         }
-        c0 += 1;
+        assert_eq!(Dice::STANDARD.num_keeps(), 462);
     }
-    distinct_keeps
-}
 
-const fn make_distinct_non_yahtzee_rolls()
--> [[u8; RollCounts::NUM_FACES]; DISTINCT_NON_YAHTZEE_ROLL_COUNTS] {
-    let mut distinct_non_yahtzee_rolls = [[0, 0, 0, 0, 0, 0]; DISTINCT_NON_YAHTZEE_ROLL_COUNTS];
-    let mut distinct_rolls_it = 0usize;
-    let mut i = 0usize;
-    while distinct_rolls_it < DISTINCT_ROLL_COUNTS {
-        if !is_yahtzee(&DISTINCT_ROLLS[distinct_rolls_it]) {
-            distinct_non_yahtzee_rolls[i] = DISTINCT_ROLLS[distinct_rolls_it];
-            i += 1;
+    #[test]
+    fn roll_transition_rows_sum_to_one() {
+        for dice in all_dice() {
+            let tables = dice.tables();
+            assert_eq!(tables.transitions().row_starts.len(), dice.num_keeps() + 1);
+            for keep_counts in dice.keeps() {
+                let row = tables.transitions().get(keep_counts);
+                assert!(row.iter().all(|&(_, p)| p > 0f64), "{keep_counts:?}");
+                let total: f64 = row.iter().map(|&(_, p)| p).sum();
+                assert!(
+                    (total - 1.0).abs() < 1e-12,
+                    "{keep_counts:?} sums to {total}."
+                );
+            }
+            // keeping every die leaves exactly one outcome, and keeping none leaves every roll.
+            let keep_all = dice.rolls()[0].subtract(&KeepCounts::none(dice)).unwrap();
+            assert_eq!(tables.transitions().get(&keep_all).len(), 1);
+            assert_eq!(
+                tables.transitions().get(&KeepCounts::none(dice)).len(),
+                dice.num_rolls()
+            );
         }
-        distinct_rolls_it += 1;
-    }
-    distinct_non_yahtzee_rolls
-}
-
-const fn make_distinct_yahtzee_rolls() -> [[u8; RollCounts::NUM_FACES]; DISTINCT_YAHTZEE_ROLL_COUNTS]
-{
-    let mut distinct_yahtzee_rolls = [[0, 0, 0, 0, 0, 0]; DISTINCT_YAHTZEE_ROLL_COUNTS];
-    let mut i = 0usize;
-    while i < RollCounts::NUM_FACES {
-        let mut roll = [0, 0, 0, 0, 0, 0];
-        roll[i] = RollCounts::NUM_DICE as u8;
-        distinct_yahtzee_rolls[i] = roll;
-        i += 1;
     }
-    distinct_yahtzee_rolls
-}
 
-const fn is_yahtzee(roll_counts: &[u8; RollCounts::NUM_FACES]) -> bool {
-    let mut i = 0usize;
-    while i < RollCounts::NUM_FACES {
-        if roll_counts[i] == RollCounts::NUM_DICE as u8 {
-            return true;
+    #[cfg(feature = "exact")]
+    #[test]
+    fn exact_transitions_match() {
+        use num_rational::BigRational;
+        use num_traits::{One, ToPrimitive};
+        for dice in all_dice() {
+            for keep_rank in 0..dice.num_keeps() {
+                let row = f64::transition_row(&dice, keep_rank);
+                let exact_row = BigRational::transition_row(&dice, keep_rank);
+                assert_eq!(row.len(), exact_row.len());
+                for (&(rank, p), (exact_rank, exact_p)) in row.iter().zip(exact_row) {
+                    assert_eq!(rank, *exact_rank);
+                    let exact_p = exact_p.to_f64().unwrap();
+                    assert!((p - exact_p).abs() <= f64::EPSILON * p, "{p} != {exact_p}");
+                }
+                let total: BigRational = exact_row.iter().map(|(_, p)| p).sum();
+                assert!(total.is_one(), "Keep {keep_rank} sums to {total}.");
+            }
+            let total: BigRational = dice.rolls().iter().map(BigRational::p_roll).sum();
+            assert!(total.is_one());
         }
-        i += 1;
     }
-    false
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn rank_is_bijection() {
-        let mut rank_to_roll_counts: [Option<RollCounts>; DISTINCT_ROLL_COUNTS] =
-            [None; DISTINCT_ROLL_COUNTS];
-        for distinct_roll in DISTINCT_ROLLS {
-            let roll_counts = RollCounts::try_from(distinct_roll)
-                .expect("These were created in such a way that they should all be valid.");
-            let rank = roll_counts.rank();
-            if rank_to_roll_counts[rank] == None {
-                rank_to_roll_counts[rank] = Some(roll_counts);
-                continue;
-            }
-            if rank_to_roll_counts[rank] == Some(roll_counts) {
-                continue;
-            }
-            assert!(
-                false,
-                "rank not injective: {:#?} collided with {:#?} at rank {}.",
-                roll_counts, rank_to_roll_counts[rank], rank
-            );
-        }
-        // Loop has ended => No collisions (injective). Now we check that every rank is assigned a roll count (surjective).
-        assert!(
-            !rank_to_roll_counts.iter().any(|&x| x == None),
-            "rank not surjective: {:#?}",
-            rank_to_roll_counts
+    fn valid_keep_counts_of_maxi_roll() {
+        let roll_counts = RollCounts::new([2, 0, 0, 1, 0, 3], Dice::MAXI).unwrap();
+        let valid_keep_counts = roll_counts.valid_keep_counts();
+        assert_eq!(valid_keep_counts.len(), 3 * 2 * 4);
+        assert_eq!(valid_keep_counts[0], KeepCounts::none(Dice::MAXI));
+        assert_eq!(
+            valid_keep_counts.last().unwrap().keep_counts(),
+            roll_counts.roll_counts()
+        );
+        assert_eq!(
+            Dice::MAXI.tables().valid_keep_counts(&roll_counts),
+            valid_keep_counts
         );
     }
 
@@ -278,6 +498,8 @@ mod tests {
             ([5, 0, 0, 0, 0, 0], [4, 0, 0, 0, 0, 0], 1.0 / 6f64),
             ([5, 0, 0, 0, 0, 0], [3, 0, 0, 0, 0, 0], 1.0 / 36f64),
             ([1, 0, 1, 1, 1, 1], [2, 0, 0, 0, 0, 0], 0f64),
+            ([0, 0, 0, 0, 0, 5], [0, 0, 0, 0, 0, 3], 1.0 / 36f64),
+            ([0, 0, 0, 0, 1, 4], [0, 0, 0, 0, 0, 5], 0f64),
         ];
         for (roll, keep, expected) in cases {
             let roll_counts = RollCounts::try_from(roll).unwrap();
@@ -0,0 +1,288 @@
+use crate::types::{Action, DiceState, RollsLeft, RuleSet, ScorecardState};
+use crate::{IndexKey, Memo, dice_dp, dice_state_index};
+use std::collections::HashMap;
+
+/// A probability distribution over a number of points, such as a final score, as a histogram
+/// indexed by points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreDistribution {
+    probabilities: Vec<f64>,
+}
+
+impl ScoreDistribution {
+    /// * `probabilities` - probabilities[x] is the probability of scoring exactly x points.
+    pub fn new(probabilities: Vec<f64>) -> Self {
+        Self { probabilities }
+    }
+
+    /// The distribution of always scoring exactly `points`.
+    pub fn point_mass(points: usize) -> Self {
+        let mut result = Self {
+            probabilities: Vec::new(),
+        };
+        result.add(points, 1f64);
+        result
+    }
+
+    /// probabilities()[x] is the probability of scoring exactly x points.
+    pub fn probabilities(&self) -> &[f64] {
+        &self.probabilities
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.probabilities
+            .iter()
+            .enumerate()
+            .map(|(x, p)| x as f64 * p)
+            .sum()
+    }
+
+    /// The smallest score x such that P(score <= x) >= q, or None if no score has a positive
+    /// probability.
+    ///
+    /// * `q` - A quantile on [0, 1], e.g. 0.5 for the median.
+    pub fn percentile(&self, q: f64) -> Option<usize> {
+        let max_score = self.probabilities.iter().rposition(|&p| p > 0f64)?;
+        let mut cumulative = 0f64;
+        for (x, p) in self.probabilities[..max_score].iter().enumerate() {
+            cumulative += p;
+            if cumulative >= q {
+                return Some(x);
+            }
+        }
+        // rounding can leave the total just short of 1.
+        Some(max_score)
+    }
+
+    /// P(score >= x).
+    pub fn p_at_least(&self, x: usize) -> f64 {
+        self.probabilities.iter().skip(x).sum()
+    }
+
+    fn add(&mut self, points: usize, p: f64) {
+        if self.probabilities.len() <= points {
+            self.probabilities.resize(points + 1, 0f64);
+        }
+        self.probabilities[points] += p;
+    }
+}
+
+/// Finds the exact distribution of the final score from the given scorecard state (before the
+/// first roll of a turn), when following the optimal-EV policy.
+///
+/// We push the distribution of points scored so far forward through the scorecard states, one
+/// layer of filled categories at a time, using the dice_dp policy of each state to find the
+/// distribution of where each turn ends up.
+///
+/// * `scorecard_state` - The state to start from. It must be reachable.
+/// * `points_so_far` - The points already scored, including bonuses, which every final score
+///   includes.
+/// * `scorecard_memo` - The solved ScorecardState -> EV table, which must hold every reachable
+///   state downstream of `scorecard_state`.
+/// * `rule_set` - The rules the table was solved with.
+pub fn score_distribution<S: Memo<ScorecardState, f64>>(
+    scorecard_state: &ScorecardState,
+    points_so_far: usize,
+    scorecard_memo: &S,
+    rule_set: &RuleSet,
+) -> ScoreDistribution {
+    if scorecard_state.is_terminal() {
+        return ScoreDistribution::point_mass(points_so_far);
+    }
+    let mut result = ScoreDistribution {
+        probabilities: Vec::new(),
+    };
+    let mut layer: HashMap<ScorecardState, ScoreDistribution> = HashMap::new();
+    layer.insert(
+        *scorecard_state,
+        ScoreDistribution::point_mass(points_so_far),
+    );
+    while !layer.is_empty() {
+        let mut next_layer: HashMap<ScorecardState, ScoreDistribution> = HashMap::new();
+        // sort so that the sums are done in the same order, and the results are the same, on
+        // every run.
+        let mut sorted_layer: Vec<(ScorecardState, ScoreDistribution)> =
+            layer.into_iter().collect();
+        sorted_layer.sort_by_key(|(scorecard_state, _)| scorecard_state.to_index());
+        for (scorecard_state, distribution) in sorted_layer {
+            for (target_scorecard_state, points, p) in
+                turn_outcomes(&scorecard_state, scorecard_memo, rule_set)
+            {
+                let target = if target_scorecard_state.is_terminal() {
+                    &mut result
+                } else {
+                    next_layer
+                        .entry(target_scorecard_state)
+                        .or_insert_with(|| ScoreDistribution {
+                            probabilities: Vec::new(),
+                        })
+                };
+                for (x, &q) in distribution.probabilities.iter().enumerate() {
+                    if q > 0f64 {
+                        target.add(x + points, p * q);
+                    }
+                }
+            }
+        }
+        layer = next_layer;
+    }
+    result
+}
+
+/// The distribution of how a turn from the given scorecard state ends when following the
+/// optimal-EV policy, as (target scorecard state, points scored, probability).
+fn turn_outcomes<S: Memo<ScorecardState, f64>>(
+    scorecard_state: &ScorecardState,
+    scorecard_memo: &S,
+    rule_set: &RuleSet,
+) -> Vec<(ScorecardState, usize, f64)> {
+    let (_, policy_memo) = dice_dp(scorecard_state, scorecard_memo, rule_set);
+    let mut outcomes: HashMap<(ScorecardState, usize), f64> = HashMap::new();
+    // the probability of being in each dice state, indexed as in dice_dp.
+    let dice = &rule_set.dice;
+    let tables = dice.tables();
+    let mut dice_probabilities =
+        vec![0f64; dice_state_index(dice, dice.num_rolls() - 1, rule_set.rerolls) + 1];
+    for roll_counts in tables.rolls() {
+        dice_probabilities[dice_state_index(dice, roll_counts.rank(), rule_set.rerolls)] =
+            roll_counts.p_roll();
+    }
+    // every transition out of a dice state with n rolls left is into one with n - 1 rolls left.
+    for raw_rolls_left in (0..=rule_set.rerolls).rev() {
+        let rolls_left = RollsLeft::try_from(raw_rolls_left).unwrap();
+        for &roll_counts in tables.rolls() {
+            let p = dice_probabilities[dice_state_index(dice, roll_counts.rank(), raw_rolls_left)];
+            if p == 0f64 {
+                continue;
+            }
+            let dice_state = DiceState {
+                roll_counts,
+                rolls_left,
+            };
+            match policy_memo
+                .get(&dice_state)
+                .expect("dice_dp has an action for every dice state of a non-terminal state.")
+            {
+                Action::Keep(keep_counts) => {
+                    for (target_roll_counts_rank, q) in tables.transitions().get(keep_counts) {
+                        dice_probabilities[dice_state_index(
+                            dice,
+                            *target_roll_counts_rank,
+                            raw_rolls_left - 1,
+                        )] += p * q;
+                    }
+                }
+                &Action::Score(score_category) => {
+                    let (category_score, bonus_score) = scorecard_state
+                        .score_value(&roll_counts, score_category, rule_set)
+                        .expect("The policy only scores valid categories.");
+                    let target_scorecard_state = scorecard_state
+                        .score(score_category, category_score)
+                        .expect("The policy only scores valid categories.");
+                    let points = category_score as usize + bonus_score as usize;
+                    *outcomes
+                        .entry((target_scorecard_state, points))
+                        .or_insert(0f64) += p;
+                }
+            }
+        }
+    }
+    let mut outcomes: Vec<(ScorecardState, usize, f64)> = outcomes
+        .into_iter()
+        .map(|((target_scorecard_state, points), p)| (target_scorecard_state, points, p))
+        .collect();
+    outcomes.sort_by_key(|&(target_scorecard_state, points, _)| {
+        (target_scorecard_state.to_index(), points)
+    });
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::JokerRule;
+    use crate::types::ScoreCategory;
+    use crate::{VecMemo, all_filled_except, scorecard_state_ev};
+
+    #[test]
+    fn chance_only_distribution() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let scorecard_state = all_filled_except(&rule_set, &[ScoreCategory::Chance]);
+        let memo: VecMemo<ScorecardState, f64> = VecMemo::new();
+        let distribution = score_distribution(&scorecard_state, 0, &memo, &rule_set);
+        let total: f64 = distribution.probabilities().iter().sum();
+        assert!((total - 1.0).abs() < 1e-12, "Total probability {total}.");
+        let ev = scorecard_state_ev(&scorecard_state, &memo, &rule_set);
+        assert!((distribution.mean() - ev).abs() < 1e-9);
+        // chance is at least 5 and at most 30.
+        assert_eq!(distribution.p_at_least(5), total);
+        assert_eq!(distribution.p_at_least(31), 0.0);
+        assert!(distribution.percentile(0.5).unwrap() > 20);
+    }
+
+    #[test]
+    fn two_turn_distribution() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let scorecard_state =
+            all_filled_except(&rule_set, &[ScoreCategory::Chance, ScoreCategory::Yahtzee]);
+        let mut memo: VecMemo<ScorecardState, f64> = VecMemo::new();
+        for target_scorecard_state in [
+            scorecard_state.score(ScoreCategory::Chance, 20).unwrap(),
+            scorecard_state.score(ScoreCategory::Yahtzee, 50).unwrap(),
+            scorecard_state.score(ScoreCategory::Yahtzee, 0).unwrap(),
+        ] {
+            let ev = scorecard_state_ev(&target_scorecard_state, &memo, &rule_set);
+            memo.set(target_scorecard_state, ev);
+        }
+        let distribution = score_distribution(&scorecard_state, 0, &memo, &rule_set);
+        let total: f64 = distribution.probabilities().iter().sum();
+        assert!((total - 1.0).abs() < 1e-12, "Total probability {total}.");
+        let ev = scorecard_state_ev(&scorecard_state, &memo, &rule_set);
+        assert!(
+            (distribution.mean() - ev).abs() < 1e-9,
+            "Mean {} but EV {ev}.",
+            distribution.mean()
+        );
+        // a yahtzee followed by a bonus yahtzee in chance.
+        assert!(distribution.p_at_least(150) > 0.0);
+    }
+
+    #[test]
+    fn terminal_distribution() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let scorecard_state = all_filled_except(&rule_set, &[]);
+        let memo: VecMemo<ScorecardState, f64> = VecMemo::new();
+        let distribution = score_distribution(&scorecard_state, 0, &memo, &rule_set);
+        assert_eq!(distribution.probabilities(), &[1.0]);
+        assert_eq!(distribution.mean(), 0.0);
+    }
+    #[test]
+    fn points_so_far_offsets_distribution() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let scorecard_state = all_filled_except(&rule_set, &[ScoreCategory::Chance]);
+        let memo: VecMemo<ScorecardState, f64> = VecMemo::new();
+        let distribution = score_distribution(&scorecard_state, 0, &memo, &rule_set);
+        let offset = score_distribution(&scorecard_state, 100, &memo, &rule_set);
+        assert_eq!(offset.probabilities()[100..], *distribution.probabilities());
+        assert!((offset.mean() - (distribution.mean() + 100.0)).abs() < 1e-9);
+        assert_eq!(
+            offset.percentile(0.5),
+            distribution.percentile(0.5).map(|x| x + 100)
+        );
+        assert_eq!(offset.p_at_least(105), distribution.p_at_least(5));
+        let terminal = all_filled_except(&rule_set, &[]);
+        assert_eq!(
+            score_distribution(&terminal, 100, &memo, &rule_set),
+            ScoreDistribution::point_mass(100)
+        );
+    }
+
+    #[test]
+    fn empty_percentile() {
+        assert_eq!(ScoreDistribution::new(vec![]).percentile(0.5), None);
+        assert_eq!(ScoreDistribution::new(vec![0.0; 3]).percentile(0.5), None);
+        // rounding leaves the total short of 1.
+        let distribution = ScoreDistribution::new(vec![0.5, 0.5 - 1e-12, 0.0]);
+        assert_eq!(distribution.percentile(1.0), Some(1));
+    }
+}
@@ -0,0 +1,175 @@
+use crate::types::{RuleSet, ScorecardState};
+use crate::{
+    DpValue, MapMemo, Memo, VecMemo, dice_dp_with_value, downstream_transitions, first_roll_value,
+    scorecard_state_ev,
+};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
+
+/// The exact DP weighs outcomes by exact probabilities, so no probability is ever rounded.
+impl DpValue for BigRational {
+    type Probability = BigRational;
+
+    fn expectation<'a, I: IntoIterator<Item = (BigRational, &'a Self)>>(outcomes: I) -> Self {
+        let mut ev = BigRational::zero();
+        for (p, v) in outcomes {
+            ev += p * v;
+        }
+        ev
+    }
+
+    fn is_better(&self, other: &Self) -> bool {
+        self > other
+    }
+
+    fn add_score(&self, points: u8) -> Self {
+        self + BigInt::from(points)
+    }
+}
+
+/// Solves scorecard states with exact rational arithmetic, to certify the EVs of the f64 solver.
+/// The denominators grow with every turn, so this is only practical for states near the end of
+/// the game, and only the states downstream of a query are solved.
+pub struct ExactSolver {
+    rule_set: RuleSet,
+    memo: MapMemo<ScorecardState, BigRational>,
+    /// Every solved state, each after all of its downstream states.
+    solved: Vec<ScorecardState>,
+}
+
+/// How far the f64 solver is from the exact EVs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorReport {
+    pub num_states: usize,
+    pub max_abs_error: f64,
+    /// The state with the largest error, or None if no states were compared.
+    pub worst_state: Option<ScorecardState>,
+}
+
+impl ExactSolver {
+    /// * `rule_set` - The rules to score rolls with.
+    pub fn new(rule_set: RuleSet) -> Self {
+        Self {
+            rule_set,
+            memo: MapMemo::default(),
+            solved: Vec::new(),
+        }
+    }
+
+    /// The exact EV of a scorecard state before the first roll of the turn.
+    pub fn ev(&mut self, scorecard_state: &ScorecardState) -> BigRational {
+        if scorecard_state.is_terminal() {
+            return BigRational::zero();
+        }
+        if let Some(ev) = self.memo.get(scorecard_state) {
+            return ev.clone();
+        }
+        self.solve_downstream(scorecard_state);
+        let (dice_ev_memo, _) = dice_dp_with_value(
+            scorecard_state,
+            &self.rule_set,
+            |target_scorecard_state, points| {
+                let transition_ev = if target_scorecard_state.is_terminal() {
+                    BigRational::zero()
+                } else {
+                    self.memo
+                        .get(target_scorecard_state)
+                        .cloned()
+                        .expect("Every downstream scorecard state must be solved first.")
+                };
+                transition_ev.add_score(points)
+            },
+        );
+        let ev = first_roll_value(&dice_ev_memo, &self.rule_set.dice, self.rule_set.rerolls);
+        self.memo.set(*scorecard_state, ev.clone());
+        self.solved.push(*scorecard_state);
+        ev
+    }
+
+    /// Compares the EVs of the f64 solver against the exact EV of every state solved so far.
+    ///
+    /// * `scorecard_memo` - The f64 ScorecardState -> EV memo, e.g. from scorecard_dp. It must hold
+    ///   every state solved so far.
+    pub fn compare<S: Memo<ScorecardState, f64>>(&self, scorecard_memo: &S) -> ErrorReport {
+        let mut report = ErrorReport {
+            num_states: 0,
+            max_abs_error: 0f64,
+            worst_state: None,
+        };
+        for scorecard_state in &self.solved {
+            let ev = scorecard_memo
+                .get(scorecard_state)
+                .copied()
+                .expect("The memo must hold every solved state.");
+            let exact_ev = self.memo.get(scorecard_state).unwrap();
+            let error = (BigRational::from_float(ev).expect("EVs are finite.") - exact_ev)
+                .abs()
+                .to_f64()
+                .unwrap();
+            report.num_states += 1;
+            if report.worst_state.is_none() || error > report.max_abs_error {
+                report.max_abs_error = error;
+                report.worst_state = Some(*scorecard_state);
+            }
+        }
+        report
+    }
+
+    /// Solves the scorecard state and everything downstream of it with both exact and f64
+    /// arithmetic, and compares them.
+    ///
+    /// * `scorecard_state` - The state to verify.
+    pub fn verify(&mut self, scorecard_state: &ScorecardState) -> ErrorReport {
+        self.ev(scorecard_state);
+        // solved is in dependency order, so the f64 solver can follow it too.
+        let mut scorecard_memo: VecMemo<ScorecardState, f64> = VecMemo::new();
+        for solved_state in &self.solved {
+            let ev = scorecard_state_ev(solved_state, &scorecard_memo, &self.rule_set);
+            scorecard_memo.set(*solved_state, ev);
+        }
+        self.compare(&scorecard_memo)
+    }
+
+    /// Solves every scorecard state that can follow a scoring from this one.
+    fn solve_downstream(&mut self, scorecard_state: &ScorecardState) {
+        let rule_set = self.rule_set;
+        for (target_scorecard_state, _) in downstream_transitions(scorecard_state, &rule_set) {
+            self.ev(&target_scorecard_state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::all_filled_except;
+    use crate::types::JokerRule;
+    use crate::types::ScoreCategory;
+
+    #[test]
+    fn chance_only_exact() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let mut solver = ExactSolver::new(rule_set);
+        let scorecard_state = all_filled_except(&rule_set, &[ScoreCategory::Chance]);
+        let ev = solver.ev(&scorecard_state);
+        // the EV of chance is a multiple of 1 / 6^15, as there are three rolls of five dice.
+        assert!((BigInt::from(6).pow(15u32) % ev.denom()).is_zero());
+        let report = solver.verify(&scorecard_state);
+        assert_eq!(report.num_states, 1);
+        assert_eq!(report.worst_state, Some(scorecard_state));
+        assert!(report.max_abs_error < 1e-12, "{report:?}");
+    }
+
+    #[test]
+    fn two_turns_exact() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let mut solver = ExactSolver::new(rule_set);
+        let scorecard_state =
+            all_filled_except(&rule_set, &[ScoreCategory::Chance, ScoreCategory::Yahtzee]);
+        let report = solver.verify(&scorecard_state);
+        // chance or yahtzee (scored or scratched) left, and the state itself.
+        assert_eq!(report.num_states, 4);
+        assert!(report.max_abs_error < 1e-12, "{report:?}");
+    }
+}
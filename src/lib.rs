@@ -1,24 +1,32 @@
+pub mod advisor;
 pub mod combinatorics;
+pub mod distribution;
+#[cfg(feature = "exact")]
+pub mod exact;
+pub mod risk;
+pub mod saved;
+pub mod scorecard;
+pub mod table;
+pub mod target;
+pub mod triple;
 pub mod types;
+pub mod win;
 pub mod yahtzee;
 
-use combinatorics::{
-    DISTINCT_KEEPS, DISTINCT_NON_YAHTZEE_ROLLS, DISTINCT_ROLL_COUNTS, DISTINCT_ROLLS,
-    DISTINCT_YAHTZEE_ROLLS,
-};
+use combinatorics::MAX_DISTINCT_ROLL_COUNTS;
+use enum_map::{Enum, EnumMap};
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::marker::PhantomData;
-use std::sync::LazyLock;
+use std::num::NonZeroUsize;
 use strum::IntoEnumIterator;
 use types::{
-    DiceState, JokerRule, KeepCounts, RollCounts, RollsLeft, ScoreCategory, ScorecardState,
+    Action, CappedUpperSectionScore, Dice, DiceState, KeepCounts, RollCounts, RollsLeft, RuleSet,
+    ScoreCategory, ScoreCategoryState, ScorecardState, Variant,
 };
 
-static ROLL_PROBABILITIES: LazyLock<VecMemo<KeepCounts, Vec<(usize, f64)>>> =
-    LazyLock::new(|| precompute_roll_probabilities_vec());
-static VALID_KEEP_COUNTS: LazyLock<VecMemo<RollCounts, Vec<KeepCounts>>> =
-    LazyLock::new(|| precompute_valid_keep_counts_vec());
+/// The yahtzee category can be unscored, scored or scratched.
+const YAHTZEE_CATEGORY_STATES: usize = 3;
 
 /// Allows me to easily swap out different memo implementations for the DP.
 ///
@@ -39,6 +47,23 @@ pub trait IndexKey {
     fn max_index() -> usize;
 }
 
+/// The value of a state in the DP, which lets the same DP optimise different objectives. The
+/// expected score is the f64 implementation.
+pub trait DpValue: Clone {
+    /// The type of the probabilities outcomes are weighed by.
+    type Probability: DpProbability;
+    /// The expected value over a distribution of outcomes, given as (probability, value) pairs
+    /// whose probabilities sum to 1.
+    fn expectation<'a, I: IntoIterator<Item = (Self::Probability, &'a Self)>>(outcomes: I) -> Self
+    where
+        Self: 'a;
+    /// Whether this value is strictly preferable to `other`. Ties keep the earlier action.
+    fn is_better(&self, other: &Self) -> bool;
+    /// This value, with `points` more points scored immediately.
+    fn add_score(&self, points: u8) -> Self;
+}
+
+#[derive(Clone)]
 pub struct MapMemo<K, V> {
     memo: HashMap<K, V>,
 }
@@ -48,9 +73,39 @@ pub struct VecMemo<K, V> {
     _phantom: PhantomData<K>,
 }
 
+/// A probability which the DP weighs outcomes by: f64, or BigRational with the exact feature so
+/// that the exact DP never rounds a probability. Each type has its own copy of the dice tables.
+pub trait DpProbability: Clone + 'static {
+    /// The (target rollcount rank, probability) of every rollcount reachable from the keep with
+    /// the given rank.
+    fn transition_row(dice: &Dice, keep_rank: usize) -> &'static [(usize, Self)];
+    /// The probability of the roll when rolling every die.
+    fn p_roll(roll_counts: &RollCounts) -> Self;
+}
+
 /// Used for profiling dice_dp
 pub struct MockScorecardMemo();
 
+impl DpValue for f64 {
+    type Probability = f64;
+
+    fn expectation<'a, I: IntoIterator<Item = (f64, &'a Self)>>(outcomes: I) -> Self {
+        let mut ev = 0f64;
+        for (p, v) in outcomes {
+            ev += p * v;
+        }
+        ev
+    }
+
+    fn is_better(&self, other: &Self) -> bool {
+        self > other
+    }
+
+    fn add_score(&self, points: u8) -> Self {
+        points as f64 + self
+    }
+}
+
 impl<K: Eq + Hash, V> Memo<K, V> for MapMemo<K, V> {
     fn get(&self, key: &K) -> Option<&V> {
         self.memo.get(key)
@@ -73,9 +128,16 @@ impl<K, V> Default for MapMemo<K, V> {
     }
 }
 
+impl<K: IndexKey, V> Default for VecMemo<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<K: IndexKey, V> Memo<K, V> for VecMemo<K, V> {
+    /// None for keys past the memo's max index, as well as keys which were never set.
     fn get(&self, key: &K) -> Option<&V> {
-        self.memo[key.to_index()].as_ref()
+        self.memo.get(key.to_index())?.as_ref()
     }
 
     fn set(&mut self, key: K, value: V) -> Option<V> {
@@ -90,258 +152,996 @@ impl<K: IndexKey, V> Memo<K, V> for VecMemo<K, V> {
 }
 
 impl<K: IndexKey, V> VecMemo<K, V> {
-    fn new() -> Self {
-        let size = K::max_index() + 1;
-        let mut vec = Vec::with_capacity(size);
-        vec.resize_with(size, || None);
+    pub fn new() -> Self {
+        Self::with_max_index(K::max_index())
+    }
+
+    /// A memo of only the keys with an index on [0..=max_index], for when fewer keys than
+    /// K::max_index() are needed.
+    pub fn with_max_index(max_index: usize) -> Self {
+        let mut vec = Vec::with_capacity(max_index + 1);
+        vec.resize_with(max_index + 1, || None);
         Self {
             memo: vec,
             _phantom: PhantomData,
         }
     }
 
-    fn raw_get(&self, key: usize) -> Option<&V> {
-        self.memo[key].as_ref()
+    pub fn raw_get(&self, key: usize) -> Option<&V> {
+        self.memo.get(key)?.as_ref()
     }
 
-    fn raw_set(&mut self, key: usize, value: V) -> Option<V> {
+    pub fn raw_set(&mut self, key: usize, value: V) -> Option<V> {
         let prev = self.memo[key].take();
         self.memo[key] = Some(value);
         prev
     }
 
-    fn raw_remove(&mut self, key: usize) -> Option<V> {
+    pub fn raw_remove(&mut self, key: usize) -> Option<V> {
         self.memo[key].take()
     }
 }
 
 impl Memo<ScorecardState, f64> for MockScorecardMemo {
-    fn get(&self, key: &ScorecardState) -> Option<&f64> {
+    fn get(&self, _key: &ScorecardState) -> Option<&f64> {
         Some(&10f64)
     }
 
-    fn set(&mut self, key: ScorecardState, value: f64) -> Option<f64> {
+    fn set(&mut self, _key: ScorecardState, _value: f64) -> Option<f64> {
         Some(10f64)
     }
 
-    fn remove(&mut self, key: &ScorecardState) -> Option<f64> {
+    fn remove(&mut self, _key: &ScorecardState) -> Option<f64> {
         Some(10f64)
     }
 }
 
 impl IndexKey for DiceState {
-    /// We use stars-and-bars to give each roll a unique number on [0..252], and then consider
-    /// rolls_left which is on [0..3].
+    /// We use stars-and-bars to give each roll a unique number on [0..Dice::num_rolls()], and then
+    /// consider rolls_left which is on [0..=RollsLeft::MAX] as the more significant digit. This way
+    /// a turn with at most n rolls left only needs the first (n + 1) * Dice::num_rolls() indices,
+    /// see dice_state_memo. max_index covers the dice with the most rolls.
     fn to_index(&self) -> usize {
-        let rank = self.roll_counts.rank();
-        rank * (RollsLeft::MAX as usize + 1) + *self.rolls_left.rolls_left() as usize
+        dice_state_index(
+            self.roll_counts.dice(),
+            self.roll_counts.rank(),
+            *self.rolls_left.rolls_left(),
+        )
     }
 
     fn max_index() -> usize {
-        DISTINCT_ROLL_COUNTS * (RollsLeft::MAX as usize + 1) - 1
+        MAX_DISTINCT_ROLL_COUNTS * (RollsLeft::MAX as usize + 1) - 1
     }
 }
 
+/// The index of the DiceState of the roll with the given rank and the given rolls left, for
+/// looking up transitions by roll rank without building the DiceState.
+pub(crate) fn dice_state_index(dice: &Dice, roll_rank: usize, rolls_left: u8) -> usize {
+    rolls_left as usize * dice.num_rolls() + roll_rank
+}
+
+/// An empty memo of every DiceState of a turn with the dice, with at most `max_rolls_left` rolls
+/// left.
+pub(crate) fn dice_state_memo<V>(dice: &Dice, max_rolls_left: u8) -> VecMemo<DiceState, V> {
+    VecMemo::with_max_index(dice_state_index(dice, dice.num_rolls() - 1, max_rolls_left))
+}
+
 impl IndexKey for RollCounts {
     fn to_index(&self) -> usize {
         self.rank()
     }
 
     fn max_index() -> usize {
-        DISTINCT_ROLL_COUNTS - 1
+        MAX_DISTINCT_ROLL_COUNTS - 1
     }
 }
 
 impl IndexKey for KeepCounts {
-    /// These indices are very sparsely distributed, so this should be used with caution.
+    /// These indices are very sparsely distributed, so this should be used with caution. Each
+    /// count is on [0..=MAX_DICE], so we treat the counts as digits in base MAX_DICE + 1.
     fn to_index(&self) -> usize {
         let mut rank = 0usize;
         for &c in self.keep_counts() {
-            rank *= 5;
+            rank *= Dice::MAX_DICE as usize + 1;
             rank += c as usize;
         }
         rank
     }
 
     fn max_index() -> usize {
-        5usize.pow(6)
+        (Dice::MAX_DICE as usize + 1).pow(RollCounts::MAX_FACES as u32) - 1
     }
 }
 
-impl KeepCounts {
-    /// For these dice kept, the rank of all possible rollcounts as a result of rerolling and their
-    /// probabilities.
-    fn roll_probabilities(&self) -> Vec<(usize, f64)> {
-        let mut vec: Vec<(usize, f64)> = Vec::new();
-        for raw_target_roll_counts in DISTINCT_ROLLS {
-            let target_roll_counts = RollCounts::try_from(raw_target_roll_counts).unwrap();
-            let p = target_roll_counts.p_roll_given_keep(&self);
-            vec.push((target_roll_counts.rank(), p));
+impl IndexKey for ScorecardState {
+    /// The capped upper section score is on [0..=cap], each of the variant's categories other than
+    /// yahtzee is either filled or not, and the yahtzee category has three states. We pack these as
+    /// mixed radix digits, with the capped upper section score as the most significant, so that the
+    /// states of a smaller cap have smaller indices. Categories outside the variant are always
+    /// filled, so they take no part. Neither does the rule set, which every state of a game
+    /// shares, so a memo indexed by these must only hold the states of one game. max_index covers
+    /// a variant with every category, at the largest cap.
+    fn to_index(&self) -> usize {
+        let variant = self.variant();
+        let mut mask = 0usize;
+        for (i, score_category) in non_yahtzee_categories(variant).enumerate() {
+            if self.score_category_state()[score_category] != ScoreCategoryState::Unscored {
+                mask |= 1 << i;
+            }
         }
-        vec
+        let yahtzee_state = self.score_category_state()[ScoreCategory::Yahtzee] as usize;
+        self.capped_upper_section_score.score() as usize * category_states(variant)
+            + yahtzee_state * (1 << non_yahtzee_categories(variant).count())
+            + mask
     }
-}
 
-fn precompute_roll_probabilities_vec() -> VecMemo<KeepCounts, Vec<(usize, f64)>> {
-    let mut memo: VecMemo<KeepCounts, Vec<(usize, f64)>> = VecMemo::new();
-    for raw_keep_counts in DISTINCT_KEEPS {
-        let keep_counts = KeepCounts::try_from(raw_keep_counts).unwrap();
-        let roll_probabilities = keep_counts.roll_probabilities();
-        memo.set(keep_counts, roll_probabilities);
+    fn max_index() -> usize {
+        (CappedUpperSectionScore::MAX_CAP as usize + 1)
+            * (YAHTZEE_CATEGORY_STATES << (ScoreCategory::LENGTH - 1))
+            - 1
     }
-    memo
 }
 
-fn precompute_valid_keep_counts_vec() -> VecMemo<RollCounts, Vec<KeepCounts>> {
-    let mut memo: VecMemo<RollCounts, Vec<KeepCounts>> = VecMemo::new();
-    for raw_roll_counts in DISTINCT_ROLLS {
-        let roll_counts = RollCounts::try_from(raw_roll_counts).unwrap();
-        let valid_keep_counts = roll_counts.valid_keep_counts();
-        memo.set(roll_counts, valid_keep_counts);
+impl ScorecardState {
+    /// The largest index of a state of a game under the rule set, for sizing a memo to the states
+    /// of one game.
+    pub fn max_index_for(rule_set: &RuleSet) -> usize {
+        (rule_set.upper_section_cap() as usize + 1) * category_states(rule_set.variant) - 1
+    }
+
+    /// The inverse of `to_index`.
+    ///
+    /// * `index` - An index on [0..=ScorecardState::max_index_for(rule_set)].
+    /// * `rule_set` - The rules of the game the state is from, for its variant and upper section
+    ///   cap.
+    pub fn from_index(index: usize, rule_set: &RuleSet) -> Self {
+        assert!(
+            index <= Self::max_index_for(rule_set),
+            "index {index} is out of range for a ScorecardState."
+        );
+        let variant = rule_set.variant;
+        let upper_section_cap = rule_set.upper_section_cap();
+        let capped_upper_section_score = CappedUpperSectionScore::new(
+            (index / category_states(variant)) as u8,
+            upper_section_cap,
+        )
+        .expect("The cap must be at most MAX_CAP.");
+        let index = index % category_states(variant);
+        let num_non_yahtzee_categories = non_yahtzee_categories(variant).count();
+        let mask = index % (1 << num_non_yahtzee_categories);
+        let yahtzee_state = index >> num_non_yahtzee_categories;
+        let mut score_category_state = EnumMap::<ScoreCategory, ScoreCategoryState>::default();
+        for (i, score_category) in non_yahtzee_categories(variant).enumerate() {
+            if mask & (1 << i) != 0 {
+                score_category_state[score_category] = ScoreCategoryState::Scored;
+            }
+        }
+        score_category_state[ScoreCategory::Yahtzee] = match yahtzee_state {
+            0 => ScoreCategoryState::Unscored,
+            1 => ScoreCategoryState::Scored,
+            _ => ScoreCategoryState::Scratched,
+        };
+        Self::new(variant, capped_upper_section_score, score_category_state)
     }
-    memo
+}
+
+/// The categories of the variant other than yahtzee, in a fixed order.
+fn non_yahtzee_categories(variant: Variant) -> impl Iterator<Item = ScoreCategory> {
+    ScoreCategory::iter().filter(move |&x| x != ScoreCategory::Yahtzee && variant.has_category(x))
+}
+
+/// The number of ways the categories of a scorecard of the variant can be filled.
+fn category_states(variant: Variant) -> usize {
+    YAHTZEE_CATEGORY_STATES << non_yahtzee_categories(variant).count()
 }
 
 /// Finds the EV of the given scorecard state. Does this by solving a finite-horizon MDP TC. Also
-/// returns the optimal policy from any dice state given this scorecard state, because it's
-/// annoying to recreate from the EV memo.
+/// returns the optimal action from every dice state given this scorecard state, because it's
+/// annoying to recreate from the EV memo. The policy is only empty if the scorecard state is
+/// terminal.
 ///
 /// Requires the EV of all downstream scorecard states to be calculated. Is intended to be used as
 /// a helper in scorecard_dp.
 ///
 /// * `scorecard_state` - The state to solve the dice DP on.
-/// * `initial_value` - A value of type V to initialize the optimisation on. This should be the
-/// minimal possible V, for example 0.0 for Yahtzee (as negative scores are impossible).
-/// * `scorecard_memo` - The current memo of ScorecardState -> V.
+/// * `scorecard_memo` - The current memo of ScorecardState -> EV.
+/// * `rule_set` - The rules to score rolls with.
 pub fn dice_dp<S: Memo<ScorecardState, f64>>(
     scorecard_state: &ScorecardState,
     scorecard_memo: &S,
-    joker_rule: JokerRule,
-) -> (
-    impl Memo<DiceState, f64>,
-    impl Memo<DiceState, &'static KeepCounts>,
-) {
-    let mut ev_memo: VecMemo<DiceState, f64> = VecMemo::new();
-    let mut policy_memo: VecMemo<DiceState, &KeepCounts> = VecMemo::new();
+    rule_set: &RuleSet,
+) -> (VecMemo<DiceState, f64>, VecMemo<DiceState, Action>) {
+    dice_dp_with_value(
+        scorecard_state,
+        rule_set,
+        |target_scorecard_state, points| {
+            let transition_ev = if !target_scorecard_state.is_terminal() {
+                scorecard_memo
+                    .get(target_scorecard_state)
+                    .copied()
+                    .expect("Our scorecard DP is working backwards, so every valid transition must be accounted for.")
+            } else {
+                0f64
+            };
+            transition_ev.add_score(points)
+        },
+    )
+}
+
+/// The dice DP of dice_dp, but generic over the value of a state, and where the value of scoring
+/// into a scorecard state is given by `score_value`. This lets other objectives, such as the
+/// probability of reaching a target score, reuse the same recursion.
+///
+/// * `scorecard_state` - The state to solve the dice DP on.
+/// * `rule_set` - The rules to score rolls with.
+/// * `score_value` - The value of scoring the given points (including bonuses) and moving to the
+///   given scorecard state.
+pub fn dice_dp_with_value<V: DpValue, F: Fn(&ScorecardState, u8) -> V>(
+    scorecard_state: &ScorecardState,
+    rule_set: &RuleSet,
+    score_value: F,
+) -> (VecMemo<DiceState, V>, VecMemo<DiceState, Action>) {
+    dice_dp_with_rolls_left(
+        scorecard_state,
+        rule_set,
+        rule_set.rerolls,
+        |target_scorecard_state, points, _| score_value(target_scorecard_state, points),
+    )
+}
+
+/// The dice DP of dice_dp_with_value, for turns with any number of rolls, and where the value of
+/// scoring may depend on how many rolls were left unused, as when rolls are saved for later turns.
+/// Only dice states with at most `max_rolls_left` rolls left are solved.
+///
+/// * `scorecard_state` - The state to solve the dice DP on.
+/// * `rule_set` - The rules to score rolls with, which must pass `RuleSet::validate` so that the
+///   points of a scoring can't overflow.
+/// * `max_rolls_left` - The rolls left after the first roll of the turn, at most RollsLeft::MAX.
+/// * `score_value` - The value of scoring the given points (including bonuses) with the given
+///   rolls left, and moving to the given scorecard state.
+pub fn dice_dp_with_rolls_left<V: DpValue, F: Fn(&ScorecardState, u8, u8) -> V>(
+    scorecard_state: &ScorecardState,
+    rule_set: &RuleSet,
+    max_rolls_left: u8,
+    score_value: F,
+) -> (VecMemo<DiceState, V>, VecMemo<DiceState, Action>) {
+    rule_set.validate().expect("The rule set must be valid.");
+    let dice = &rule_set.dice;
+    let tables = dice.tables();
+    let mut value_memo: VecMemo<DiceState, V> = dice_state_memo(dice, max_rolls_left);
+    let mut policy_memo: VecMemo<DiceState, Action> = dice_state_memo(dice, max_rolls_left);
     let valid_non_yahtzee_score_categories = scorecard_state.valid_non_yahtzee_score_categories();
+    let (yahtzee_rolls, non_yahtzee_rolls): (Vec<&RollCounts>, Vec<&RollCounts>) = tables
+        .rolls()
+        .iter()
+        .partition(|x| x.is_yahtzee().is_some());
     // initialise memo with all transitions out of this scorecard_state
     // the non-yahtzee score categories have simpler rules which can be precomputed
-    for raw_non_yahtzee_roll_counts in DISTINCT_NON_YAHTZEE_ROLLS {
-        let roll_counts = RollCounts::try_from(raw_non_yahtzee_roll_counts).unwrap();
-        for raw_rolls_left in 0..=RollsLeft::MAX {
+    for &roll_counts in non_yahtzee_rolls {
+        for raw_rolls_left in 0..=max_rolls_left {
             let rolls_left = RollsLeft::try_from(raw_rolls_left).unwrap();
             let dice_state = DiceState {
-                roll_counts: roll_counts,
-                rolls_left: rolls_left,
+                roll_counts,
+                rolls_left,
             };
-            // for each direct transition, the EV is the immediate score + the EV of that
-            // transition.
-            let mut best_ev = 0f64;
+            // for each direct transition, the value is given by score_value.
+            let mut best: Option<(V, Action)> = None;
             for &score_category in &valid_non_yahtzee_score_categories {
                 let (category_score, bonus_score) = scorecard_state
-                    .score_value(&roll_counts, score_category, joker_rule)
+                    .score_value(&roll_counts, score_category, rule_set)
                     .expect("We are iterating through valid categories.");
                 let target_scorecard_state = scorecard_state
                     .score(score_category, category_score)
                     .expect("This is a valid score category.");
-                let transition_ev = if !target_scorecard_state.is_terminal() {
-                    scorecard_memo
-                        .get(&target_scorecard_state
-                        )
-                        .copied()
-                        .expect("Our scorecard DP is working backwards, so every valid transition must be accounted for.")
-                } else {
-                    0f64
-                };
-                let total_ev = (category_score + bonus_score) as f64 + transition_ev;
-                if total_ev > best_ev {
-                    best_ev = total_ev;
+                let value = score_value(
+                    &target_scorecard_state,
+                    category_score + bonus_score,
+                    raw_rolls_left,
+                );
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_value, _)| value.is_better(best_value))
+                {
+                    best = Some((value, Action::Score(score_category)));
                 }
             }
-            ev_memo.set(dice_state, best_ev);
+            if let Some((best_value, best_action)) = best {
+                value_memo.set(dice_state.clone(), best_value);
+                policy_memo.set(dice_state, best_action);
+            }
         }
     }
-    for raw_yahtzee_roll_counts in DISTINCT_YAHTZEE_ROLLS {
-        let roll_counts = RollCounts::try_from(raw_yahtzee_roll_counts).unwrap();
-        for raw_rolls_left in 0..=RollsLeft::MAX {
+    for &roll_counts in yahtzee_rolls {
+        for raw_rolls_left in 0..=max_rolls_left {
             let rolls_left = RollsLeft::try_from(raw_rolls_left).unwrap();
             let dice_state = DiceState {
-                roll_counts: roll_counts,
-                rolls_left: rolls_left,
+                roll_counts,
+                rolls_left,
             };
-            // for each direct transition, the EV is the immediate score + the EV of that
-            // transition.
-            let mut best_ev = 0f64;
-            for score_category in scorecard_state.valid_score_categories(&roll_counts, joker_rule) {
+            // for each direct transition, the value is given by score_value.
+            let mut best: Option<(V, Action)> = None;
+            for score_category in scorecard_state.valid_score_categories(&roll_counts, rule_set) {
                 let (category_score, bonus_score) = scorecard_state
-                    .score_value(&roll_counts, score_category, joker_rule)
+                    .score_value(&roll_counts, score_category, rule_set)
                     .expect("We are iterating through valid categories.");
                 let target_scorecard_state = scorecard_state
                     .score(score_category, category_score)
                     .expect("This is a valid score category.");
-                let transition_ev = if !target_scorecard_state.is_terminal() {
-                    scorecard_memo
-                        .get(&target_scorecard_state
-                        )
-                        .copied()
-                        .expect("Our scorecard DP is working backwards, so every valid transition must be accounted for.")
-                } else {
-                    0f64
-                };
-                let total_ev = (category_score + bonus_score) as f64 + transition_ev;
-                if total_ev > best_ev {
-                    best_ev = total_ev;
+                let value = score_value(
+                    &target_scorecard_state,
+                    category_score + bonus_score,
+                    raw_rolls_left,
+                );
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_value, _)| value.is_better(best_value))
+                {
+                    best = Some((value, Action::Score(score_category)));
                 }
             }
-            ev_memo.set(dice_state, best_ev);
+            if let Some((best_value, best_action)) = best {
+                value_memo.set(dice_state.clone(), best_value);
+                policy_memo.set(dice_state, best_action);
+            }
         }
     }
+    if scorecard_state.is_terminal() {
+        // there is nothing to score, so there is nothing to roll for either.
+        return (value_memo, policy_memo);
+    }
+    reroll_dp(
+        &mut value_memo,
+        &mut policy_memo,
+        dice,
+        max_rolls_left,
+        Action::Keep,
+    );
+    (value_memo, policy_memo)
+}
+
+/// The reroll passes of the dice DP. Every dice state with at most `max_rolls_left` rolls left
+/// must already hold the value of scoring it (and the scoring action in the policy), which is then
+/// replaced by the value of the best of scoring and every keep.
+///
+/// * `value_memo` - The DiceState -> value memo, holding the value of scoring each dice state.
+/// * `policy_memo` - The DiceState -> action memo, holding the best scoring of each dice state.
+/// * `dice` - The dice rolled.
+/// * `max_rolls_left` - The rolls left after the first roll of the turn.
+/// * `keep_action` - The action of following a keep.
+pub(crate) fn reroll_dp<V: DpValue, A, K: Fn(KeepCounts) -> A>(
+    value_memo: &mut VecMemo<DiceState, V>,
+    policy_memo: &mut VecMemo<DiceState, A>,
+    dice: &Dice,
+    max_rolls_left: u8,
+    keep_action: K,
+) {
+    let tables = dice.tables();
     // Note that since every state is a potential terminal state (we can choose to score our dice
-    // at any time), we must check whether immediate scoring has higher EV than the EV of any
-    // expected transition.
-    for raw_roll_counts in DISTINCT_ROLLS {
-        let roll_counts = RollCounts::try_from(raw_roll_counts).unwrap();
-        for raw_rolls_left in 1..=RollsLeft::MAX {
-            let rolls_left = RollsLeft::try_from(raw_rolls_left).unwrap();
+    // at any time), we must check whether immediate scoring has higher value than the value of
+    // any expected transition.
+    // Every transition out of a dice state with n rolls left is into a dice state with n - 1 rolls
+    // left, so we must finish each value of rolls_left before moving onto the next.
+    for raw_rolls_left in 1..=max_rolls_left {
+        let rolls_left = RollsLeft::try_from(raw_rolls_left).unwrap();
+        // Many rolls share the same keeps, so we calculate the value of following each keep once,
+        // indexed by keep rank, before choosing the best keep of each roll.
+        let keep_values: Vec<V> = (0..dice.num_keeps())
+            .map(|keep_rank| {
+                V::expectation(V::Probability::transition_row(dice, keep_rank).iter().map(
+                    |(target_roll_counts_rank, p)| {
+                        let memo_idx =
+                            dice_state_index(dice, *target_roll_counts_rank, raw_rolls_left - 1);
+                        (p.clone(), value_memo.raw_get(memo_idx).expect("Our dice DP is working backwards, so every valid transition must be accounted for."))
+                    },
+                ))
+            })
+            .collect();
+        for &roll_counts in tables.rolls() {
             let dice_state = DiceState {
-                roll_counts: roll_counts,
-                rolls_left: rolls_left,
+                roll_counts,
+                rolls_left,
             };
             // The following ensures we choose to score prematurely if it's optimal. It is what
-            // necessitates the earlier loop that calculates the EV of scoring each possible dice
-            // state.
-            let mut best_ev = ev_memo
+            // necessitates the earlier loop that calculates the value of scoring each possible
+            // dice state.
+            let mut best_value = value_memo
                 .get(&dice_state)
-                .copied()
                 .expect("We initialized the memo with every possible dicestate");
             let mut best_transition: Option<&KeepCounts> = None;
             // over all possible dice transitions (keep_counts)...
-            for keep_counts in VALID_KEEP_COUNTS.get(&roll_counts).unwrap() {
-                // calculate the EV of following that transition
-                let mut ev = 0f64;
-                for (target_roll_counts_rank, p) in ROLL_PROBABILITIES.get(keep_counts).unwrap() {
-                    let target_rolls_left = (raw_rolls_left - 1) as usize;
-                    let memo_idx =
-                        target_roll_counts_rank * (RollsLeft::MAX as usize + 1) + target_rolls_left;
-                    ev = ev + p * ev_memo.raw_get(memo_idx).copied().expect("Our dice DP is working backwards, so every valid transition must be accounted for.");
-                }
-                if ev > best_ev {
-                    best_ev = ev;
+            for keep_counts in tables.valid_keep_counts(&roll_counts) {
+                let value = &keep_values[keep_counts.rank()];
+                if value.is_better(best_value) {
+                    best_value = value;
                     best_transition = Some(keep_counts);
                 }
             }
-            ev_memo.set(dice_state.clone(), best_ev);
-            if let Some(concrete_transition) = best_transition {
-                policy_memo.set(dice_state, concrete_transition);
+            let best_value = best_value.clone();
+            value_memo.set(dice_state.clone(), best_value);
+            if let Some(&concrete_transition) = best_transition {
+                policy_memo.set(dice_state, keep_action(concrete_transition));
+            }
+        }
+    }
+}
+
+/// Builds the scorecard DP memo from ScorecardState -> EV, where the EV of a scorecard state is
+/// taken before the first roll of the turn. Terminal states have an EV of 0 and are not stored,
+/// and neither are unreachable states or states of other variants.
+/// Also returns the number of states that were solved.
+///
+/// Panics if the rule set saves rolls between turns, see `saved::SavedRollsSolver`.
+///
+/// * `rule_set` - The rules to score rolls with.
+pub fn scorecard_dp(rule_set: &RuleSet) -> (impl Memo<ScorecardState, f64> + use<>, usize) {
+    scorecard_dp_parallel(rule_set, NonZeroUsize::MIN)
+}
+
+/// The same as scorecard_dp, but solves each layer of scorecard states (those with the same number
+/// of categories filled) across multiple threads. Every state is solved with exactly the same
+/// arithmetic as on a single thread, so the EVs are bit-identical regardless of `num_threads`.
+///
+/// * `rule_set` - The rules to score rolls with.
+/// * `num_threads` - The number of threads to solve each layer with, for example
+///   `std::thread::available_parallelism()`.
+pub fn scorecard_dp_parallel(
+    rule_set: &RuleSet,
+    num_threads: NonZeroUsize,
+) -> (impl Memo<ScorecardState, f64> + use<>, usize) {
+    assert_eq!(
+        rule_set.max_saved_rolls, 0,
+        "Saved rolls are not part of the scorecard state, use SavedRollsSolver instead."
+    );
+    let mut memo: VecMemo<ScorecardState, f64> =
+        VecMemo::with_max_index(ScorecardState::max_index_for(rule_set));
+    let mut num_solved = 0usize;
+    // every scoring fills exactly one category, so every transition out of a state with n
+    // categories filled is into a state with n + 1 filled.
+    for num_filled in (0..rule_set.variant.num_categories()).rev() {
+        // every transition out of a reachable state is into a reachable state, so skipping the
+        // unreachable states never leaves a hole in the memo that dice_dp needs.
+        let layer: Vec<ScorecardState> = scorecard_states_with_filled(num_filled, rule_set)
+            .into_iter()
+            .filter(|x| x.is_reachable(&rule_set.dice))
+            .collect();
+        let evs = solve_layer(&layer, &memo, rule_set, num_threads);
+        for (scorecard_state, ev) in layer.into_iter().zip(evs) {
+            memo.set(scorecard_state, ev);
+            num_solved += 1;
+        }
+    }
+    (memo, num_solved)
+}
+
+/// Solves the EV of each scorecard state in the layer, in order. None of the states may depend on
+/// each other, so we can split them into contiguous chunks with one thread per chunk.
+fn solve_layer<S: Memo<ScorecardState, f64> + Sync>(
+    layer: &[ScorecardState],
+    scorecard_memo: &S,
+    rule_set: &RuleSet,
+    num_threads: NonZeroUsize,
+) -> Vec<f64> {
+    let solve = |chunk: &[ScorecardState]| -> Vec<f64> {
+        chunk
+            .iter()
+            .map(|scorecard_state| scorecard_state_ev(scorecard_state, scorecard_memo, rule_set))
+            .collect()
+    };
+    if num_threads.get() == 1 || layer.len() <= 1 {
+        return solve(layer);
+    }
+    let chunk_size = layer.len().div_ceil(num_threads.get());
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = layer
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || solve(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("A solver thread panicked."))
+            .collect()
+    })
+}
+
+/// The EV of a scorecard state before the first roll of the turn, i.e. the EV of each dice state
+/// with all rolls left weighted by the probability of rolling it.
+fn scorecard_state_ev<S: Memo<ScorecardState, f64>>(
+    scorecard_state: &ScorecardState,
+    scorecard_memo: &S,
+    rule_set: &RuleSet,
+) -> f64 {
+    let (dice_ev_memo, _) = dice_dp(scorecard_state, scorecard_memo, rule_set);
+    first_roll_value(&dice_ev_memo, &rule_set.dice, rule_set.rerolls)
+}
+
+/// Every transition out of the scorecard state, as (target scorecard state, points scored with
+/// bonuses), over every roll and every valid category of it. Transitions repeat across rolls.
+///
+/// Solvers with too many states to solve layer by layer, such as TargetSolver, instead solve each
+/// state on demand: they first solve every target state of these transitions (recursively), then
+/// the state itself, and cache it. Every state is then solved once, and after everything
+/// downstream of it, as the dice DP requires.
+///
+/// * `scorecard_state` - The state before the scoring.
+/// * `rule_set` - The rules to score rolls with.
+pub(crate) fn downstream_transitions(
+    scorecard_state: &ScorecardState,
+    rule_set: &RuleSet,
+) -> impl Iterator<Item = (ScorecardState, u8)> {
+    rule_set.dice.rolls().iter().flat_map(move |roll_counts| {
+        scorecard_state
+            .valid_score_categories(roll_counts, rule_set)
+            .into_iter()
+            .map(move |score_category| {
+                let (category_score, bonus_score) = scorecard_state
+                    .score_value(roll_counts, score_category, rule_set)
+                    .expect("We are iterating through valid categories.");
+                let target_scorecard_state = scorecard_state
+                    .score(score_category, category_score)
+                    .expect("This is a valid score category.");
+                (target_scorecard_state, category_score + bonus_score)
+            })
+    })
+}
+
+/// The value of each dice state with all rolls left, weighted by the probability of rolling it.
+///
+/// * `dice_value_memo` - The DiceState -> value memo from the dice DP.
+/// * `dice` - The dice rolled.
+/// * `rolls_left` - The rolls left after the first roll of the turn.
+pub(crate) fn first_roll_value<V: DpValue, M: Memo<DiceState, V>>(
+    dice_value_memo: &M,
+    dice: &Dice,
+    rolls_left: u8,
+) -> V {
+    let rolls_left = RollsLeft::try_from(rolls_left).expect("The rule set has too many rerolls.");
+    V::expectation(dice.rolls().iter().map(|&roll_counts| {
+        let dice_state = DiceState {
+            roll_counts,
+            rolls_left,
+        };
+        (
+            V::Probability::p_roll(&roll_counts),
+            dice_value_memo
+                .get(&dice_state)
+                .expect("dice_dp fills in every dice state."),
+        )
+    }))
+}
+
+/// Every ScorecardState of a game under the rule set with exactly `num_filled` of the variant's
+/// categories scored or scratched. Includes states which are unreachable in practice, such as an
+/// upper section score of 1 without aces scored.
+fn scorecard_states_with_filled(num_filled: usize, rule_set: &RuleSet) -> Vec<ScorecardState> {
+    let upper_section_cap = rule_set.upper_section_cap();
+    let categories: Vec<ScoreCategory> = ScoreCategory::iter()
+        .filter(|&x| rule_set.variant.has_category(x))
+        .collect();
+    let mut scorecard_states = Vec::new();
+    for mask in 0u32..(1 << categories.len()) {
+        if mask.count_ones() as usize != num_filled {
+            continue;
+        }
+        let mut score_category_state = EnumMap::<ScoreCategory, ScoreCategoryState>::default();
+        for (i, &score_category) in categories.iter().enumerate() {
+            if mask & (1 << i) != 0 {
+                score_category_state[score_category] = ScoreCategoryState::Scored;
+            }
+        }
+        let mut variants = vec![score_category_state];
+        if score_category_state[ScoreCategory::Yahtzee] == ScoreCategoryState::Scored {
+            let mut scratched = score_category_state;
+            scratched[ScoreCategory::Yahtzee] = ScoreCategoryState::Scratched;
+            variants.push(scratched);
+        }
+        for score_category_state in variants {
+            for raw_capped_upper_section_score in 0..=upper_section_cap {
+                let capped_upper_section_score =
+                    CappedUpperSectionScore::new(raw_capped_upper_section_score, upper_section_cap)
+                        .expect("The cap must be at most MAX_CAP.");
+                scorecard_states.push(ScorecardState::new(
+                    rule_set.variant,
+                    capped_upper_section_score,
+                    score_category_state,
+                ));
             }
         }
     }
-    (ev_memo, policy_memo)
+    scorecard_states
+}
+
+/// The empty scorecard of the rule set's variant, with every box except those in `open` scored 0.
+#[cfg(test)]
+pub(crate) fn all_filled_except(rule_set: &RuleSet, open: &[ScoreCategory]) -> ScorecardState {
+    let mut scorecard_state = ScorecardState::new_game(rule_set);
+    for score_category in scorecard_state
+        .valid_non_yahtzee_score_categories()
+        .into_iter()
+        .filter(|x| !open.contains(x))
+    {
+        scorecard_state = scorecard_state
+            .score(score_category, 0)
+            .expect("Each open box is filled once.");
+    }
+    scorecard_state
 }
 
-/// Builds the scorecard DP memo from ScorecardState -> EV.
-pub fn scorecard_dp() -> impl Memo<ScorecardState, f64> {
-    let mut memo: MapMemo<ScorecardState, f64> = MapMemo::default();
-    todo!();
-    memo
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::JokerRule;
+    use std::collections::HashSet;
+
+    /// The Maxi Yatzy rule set, with the largest cap and every category.
+    fn maxi_yatzy() -> RuleSet {
+        RuleSet {
+            dice: Dice::MAXI,
+            upper_section_bonus_threshold: CappedUpperSectionScore::MAX_CAP,
+            ..RuleSet::yatzy()
+        }
+    }
+
+    #[test]
+    fn scorecard_state_index_round_trip() {
+        for rule_set in [
+            RuleSet::standard(JokerRule::Forced),
+            RuleSet::yacht(),
+            maxi_yatzy(),
+        ] {
+            for index in 0..=ScorecardState::max_index_for(&rule_set) {
+                let scorecard_state = ScorecardState::from_index(index, &rule_set);
+                assert_eq!(scorecard_state.to_index(), index, "{scorecard_state:?}");
+            }
+        }
+        assert_eq!(
+            ScorecardState::max_index_for(&maxi_yatzy()),
+            ScorecardState::max_index()
+        );
+    }
+
+    #[test]
+    fn scorecard_state_index_covers_every_state() {
+        for rule_set in [RuleSet::standard(JokerRule::Forced), RuleSet::yacht()] {
+            let mut seen = vec![false; ScorecardState::max_index_for(&rule_set) + 1];
+            for num_filled in 0..=rule_set.variant.num_categories() {
+                for scorecard_state in scorecard_states_with_filled(num_filled, &rule_set) {
+                    assert_eq!(scorecard_state.num_filled(), num_filled);
+                    let index = scorecard_state.to_index();
+                    assert!(
+                        !seen[index],
+                        "{scorecard_state:?} collided at index {index}."
+                    );
+                    assert_eq!(
+                        ScorecardState::from_index(index, &rule_set),
+                        scorecard_state
+                    );
+                    seen[index] = true;
+                }
+            }
+            assert!(seen.iter().all(|&x| x), "Some index has no ScorecardState.");
+        }
+        // the standard game only indexes its own 13 categories.
+        assert_eq!(
+            ScorecardState::max_index_for(&RuleSet::standard(JokerRule::Forced)) + 1,
+            64 * 3 * (1 << 12)
+        );
+        assert_eq!(ScorecardState::max_index() + 1, 85 * 3 * (1 << 14));
+    }
+
+    #[test]
+    fn reachable_states_closed_under_scoring() {
+        let rule_set = RuleSet::standard(JokerRule::Forced);
+        let max_index = ScorecardState::max_index_for(&rule_set);
+        let mut num_reachable = 0usize;
+        for index in 0..=max_index {
+            let scorecard_state = ScorecardState::from_index(index, &rule_set);
+            if !scorecard_state.is_reachable(&Dice::STANDARD) {
+                continue;
+            }
+            num_reachable += 1;
+            // only upper section scorings change the capped upper section score.
+            for score_category in scorecard_state
+                .valid_non_yahtzee_score_categories()
+                .into_iter()
+                .filter(|x| x.is_upper_section())
+            {
+                let face = score_category.into_usize() as u8 + 1;
+                for n in 0..=Dice::STANDARD.num_dice() {
+                    let target_scorecard_state =
+                        scorecard_state.score(score_category, face * n).unwrap();
+                    assert!(
+                        target_scorecard_state.is_reachable(&Dice::STANDARD),
+                        "{target_scorecard_state:?} is reachable from {scorecard_state:?}."
+                    );
+                }
+            }
+        }
+        assert!(num_reachable < max_index + 1);
+    }
+
+    #[test]
+    fn solve_layer_threads_bit_identical() {
+        // only the last layer can be solved without a memo of downstream states.
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let layer: Vec<ScorecardState> =
+            scorecard_states_with_filled(rule_set.variant.num_categories() - 1, &rule_set)
+                .into_iter()
+                .filter(|x| x.is_reachable(&Dice::STANDARD))
+                .step_by(97)
+                .collect();
+        let memo: VecMemo<ScorecardState, f64> = VecMemo::new();
+        let single = solve_layer(
+            &layer,
+            &memo,
+            &RuleSet::standard(JokerRule::FreeChoice),
+            NonZeroUsize::MIN,
+        );
+        let multi = solve_layer(
+            &layer,
+            &memo,
+            &RuleSet::standard(JokerRule::FreeChoice),
+            NonZeroUsize::new(3).unwrap(),
+        );
+        assert_eq!(single.len(), layer.len());
+        let single_bits: Vec<u64> = single.iter().map(|x| x.to_bits()).collect();
+        let multi_bits: Vec<u64> = multi.iter().map(|x| x.to_bits()).collect();
+        assert_eq!(single_bits, multi_bits);
+    }
+
+    /// The EV objective, but through the generic DpValue plumbing rather than f64 itself.
+    #[derive(Debug, Clone)]
+    struct WrappedEv(f64);
+
+    impl DpValue for WrappedEv {
+        type Probability = f64;
+
+        fn expectation<'a, I: IntoIterator<Item = (f64, &'a Self)>>(outcomes: I) -> Self {
+            WrappedEv(f64::expectation(
+                outcomes.into_iter().map(|(p, WrappedEv(v))| (p, v)),
+            ))
+        }
+
+        fn is_better(&self, other: &Self) -> bool {
+            self.0.is_better(&other.0)
+        }
+
+        fn add_score(&self, points: u8) -> Self {
+            WrappedEv(self.0.add_score(points))
+        }
+    }
+
+    /// MockScorecardMemo values every state at 10.
+    #[test]
+    fn dice_dp_generic_value_matches_ev() {
+        let scorecard_state = ScorecardState::default();
+        let (ev_memo, policy_memo) = dice_dp(
+            &scorecard_state,
+            &MockScorecardMemo(),
+            &RuleSet::standard(JokerRule::Forced),
+        );
+        let (wrapped_memo, wrapped_policy_memo) = dice_dp_with_value(
+            &scorecard_state,
+            &RuleSet::standard(JokerRule::Forced),
+            |_, points| WrappedEv(10f64).add_score(points),
+        );
+        for &roll_counts in Dice::STANDARD.rolls() {
+            for raw_rolls_left in 0..=RuleSet::default().rerolls {
+                let dice_state = DiceState {
+                    roll_counts,
+                    rolls_left: RollsLeft::try_from(raw_rolls_left).unwrap(),
+                };
+                assert_eq!(
+                    ev_memo.get(&dice_state).unwrap().to_bits(),
+                    wrapped_memo.get(&dice_state).unwrap().0.to_bits()
+                );
+                assert_eq!(
+                    policy_memo.get(&dice_state),
+                    wrapped_policy_memo.get(&dice_state)
+                );
+            }
+        }
+        assert_eq!(
+            first_roll_value(&ev_memo, &Dice::STANDARD, 2).to_bits(),
+            first_roll_value(&wrapped_memo, &Dice::STANDARD, 2)
+                .0
+                .to_bits()
+        );
+    }
+
+    #[test]
+    fn keep_values_match_direct_evaluation() {
+        let scorecard_state = ScorecardState::default();
+        // every downstream scorecard state is worth 10.
+        let (ev_memo, _) = dice_dp_with_value(
+            &scorecard_state,
+            &RuleSet::standard(JokerRule::FreeChoice),
+            |_, points| 10f64.add_score(points),
+        );
+        for raw_rolls_left in 1..=RuleSet::default().rerolls {
+            for &roll_counts in Dice::STANDARD.rolls() {
+                // scoring immediately is worth the same as with no rolls left.
+                let mut expected = *ev_memo
+                    .get(&DiceState {
+                        roll_counts,
+                        rolls_left: RollsLeft::try_from(0).unwrap(),
+                    })
+                    .unwrap();
+                // evaluate every keep of this roll from scratch.
+                for keep_counts in roll_counts.valid_keep_counts() {
+                    let mut ev = 0f64;
+                    for &(target_roll_counts_rank, p) in
+                        Dice::STANDARD.tables().transitions().get(&keep_counts)
+                    {
+                        let memo_idx = dice_state_index(
+                            &Dice::STANDARD,
+                            target_roll_counts_rank,
+                            raw_rolls_left - 1,
+                        );
+                        ev += p * ev_memo.raw_get(memo_idx).unwrap();
+                    }
+                    expected = expected.max(ev);
+                }
+                let dice_state = DiceState {
+                    roll_counts,
+                    rolls_left: RollsLeft::try_from(raw_rolls_left).unwrap(),
+                };
+                assert_eq!(
+                    ev_memo.get(&dice_state).unwrap().to_bits(),
+                    expected.to_bits(),
+                    "{dice_state:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dice_dp_policy_complete() {
+        let scorecard_state = ScorecardState::default()
+            .score(ScoreCategory::Yahtzee, 50)
+            .unwrap();
+        let (_, policy_memo) = dice_dp(
+            &scorecard_state,
+            &MockScorecardMemo(),
+            &RuleSet::standard(JokerRule::Forced),
+        );
+        for &roll_counts in Dice::STANDARD.rolls() {
+            for raw_rolls_left in 0..=RuleSet::default().rerolls {
+                let dice_state = DiceState {
+                    roll_counts,
+                    rolls_left: RollsLeft::try_from(raw_rolls_left).unwrap(),
+                };
+                let action = policy_memo.get(&dice_state);
+                assert!(action.is_some(), "No action for {dice_state:?}.");
+                if raw_rolls_left == 0 {
+                    assert!(
+                        matches!(action, Some(Action::Score(_))),
+                        "Must score {dice_state:?}, got {action:?}."
+                    );
+                }
+            }
+        }
+        // with the yahtzee box filled, the forced joker rule leaves no choice of category for a
+        // yahtzee whose upper section box is open.
+        let dice_state = DiceState {
+            roll_counts: RollCounts::try_from([0, 0, 0, 0, 0, 5]).unwrap(),
+            rolls_left: RollsLeft::try_from(0).unwrap(),
+        };
+        assert_eq!(
+            policy_memo.get(&dice_state),
+            Some(&Action::Score(ScoreCategory::Sixes))
+        );
+    }
+
+    #[test]
+    fn keep_counts_index_is_injective() {
+        for dice in [Dice::STANDARD, Dice::MAXI] {
+            let mut seen = vec![false; KeepCounts::max_index() + 1];
+            for keep_counts in dice.keeps() {
+                let index = keep_counts.to_index();
+                assert!(!seen[index], "{keep_counts:?} collided at index {index}.");
+                seen[index] = true;
+            }
+        }
+    }
+
+    #[test]
+    fn yatzy_only_pairs_left() {
+        let rule_set = RuleSet::yatzy();
+        let mut scorecard_state = ScorecardState::new_game(&rule_set);
+        for score_category in ScoreCategory::iter()
+            .filter(|x| !matches!(x, ScoreCategory::OnePair | ScoreCategory::TwoPairs))
+        {
+            scorecard_state = scorecard_state.score(score_category, 0).unwrap();
+        }
+        assert_eq!(scorecard_state.variant(), Variant::Yatzy);
+        let mut memo: VecMemo<ScorecardState, f64> = VecMemo::new();
+        for (score_category, points) in [(ScoreCategory::OnePair, 12), (ScoreCategory::TwoPairs, 0)]
+        {
+            let one_left = scorecard_state.score(score_category, points).unwrap();
+            let ev = scorecard_state_ev(&one_left, &memo, &rule_set);
+            memo.set(one_left, ev);
+        }
+        let ev = scorecard_state_ev(&scorecard_state, &memo, &rule_set);
+        memo.set(scorecard_state, ev);
+        // at most a pair of sixes and two pairs of sixes and fives.
+        assert!(ev > 0.0 && ev < 12.0 + 22.0, "Got EV {ev}.");
+        let two_pairs_left = scorecard_state.score(ScoreCategory::OnePair, 12).unwrap();
+        let (dice_ev_memo, policy_memo) = dice_dp(&two_pairs_left, &memo, &rule_set);
+        let dice_state = DiceState {
+            roll_counts: RollCounts::try_from([0, 0, 0, 1, 2, 2]).unwrap(),
+            rolls_left: RollsLeft::try_from(0).unwrap(),
+        };
+        assert_eq!(dice_ev_memo.get(&dice_state), Some(&22.0));
+        assert_eq!(
+            policy_memo.get(&dice_state),
+            Some(&Action::Score(ScoreCategory::TwoPairs))
+        );
+    }
+
+    #[test]
+    fn configurable_rerolls() {
+        // a die is worth 3.5 without rerolls, and 4.25 with one.
+        for (rerolls, expected) in [(0, 17.5), (1, 21.25), (2, 70.0 / 3.0)] {
+            let rule_set = RuleSet {
+                rerolls,
+                ..RuleSet::standard(JokerRule::FreeChoice)
+            };
+            let scorecard_state = all_filled_except(&rule_set, &[ScoreCategory::Chance]);
+            let ev = scorecard_state_ev(&scorecard_state, &VecMemo::new(), &rule_set);
+            assert!(
+                (ev - expected).abs() < 1e-9,
+                "Got EV {ev} with {rerolls} rerolls."
+            );
+        }
+    }
+
+    #[test]
+    fn maxi_chance_only() {
+        let rule_set = RuleSet {
+            dice: Dice::MAXI,
+            ..RuleSet::yatzy()
+        };
+        let scorecard_state = all_filled_except(&rule_set, &[ScoreCategory::Chance]);
+        let ev = scorecard_state_ev(&scorecard_state, &VecMemo::new(), &rule_set);
+        // each die is rerolled independently, and is worth 14/3 with three rolls.
+        assert!((ev - 28.0).abs() < 1e-9, "Got EV {ev}.");
+    }
+
+    #[test]
+    fn downstream_transitions_of_last_boxes() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let scorecard_state =
+            all_filled_except(&rule_set, &[ScoreCategory::Chance, ScoreCategory::Sixes]);
+        let transitions: Vec<(ScorecardState, u8)> =
+            downstream_transitions(&scorecard_state, &rule_set).collect();
+        // every roll can go in either box.
+        assert_eq!(transitions.len(), 2 * rule_set.dice.num_rolls());
+        let chance = scorecard_state.score(ScoreCategory::Chance, 0).unwrap();
+        for (target_scorecard_state, points) in &transitions {
+            if *target_scorecard_state != chance {
+                let sixes = scorecard_state.score(ScoreCategory::Sixes, *points);
+                assert_eq!(sixes, Ok(*target_scorecard_state));
+            }
+        }
+        let targets: HashSet<ScorecardState> = transitions.iter().map(|&(x, _)| x).collect();
+        // chance, or none to five sixes.
+        assert_eq!(targets.len(), 1 + 6);
+        assert_eq!(transitions.iter().map(|&(_, x)| x).max(), Some(30));
+    }
+
+    /// Solving the whole game takes a long time, so run this with
+    /// `cargo test --release -- --ignored`. 254.5896 is the well-known optimal EV of solitaire
+    /// yahtzee.
+    #[test]
+    #[ignore]
+    fn scorecard_dp_empty_scorecard_ev() {
+        let num_threads = std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN);
+        let (memo, num_solved) =
+            scorecard_dp_parallel(&RuleSet::standard(JokerRule::FreeChoice), num_threads);
+        let ev = *memo.get(&ScorecardState::default()).unwrap();
+        assert!(
+            (ev - 254.5896).abs() < 1e-4,
+            "Got EV {ev} from {num_solved} solved states."
+        );
+    }
 }
@@ -1,12 +1,12 @@
-use yahtzee_solver::types::{JokerRule, ScorecardState};
+use yahtzee_solver::types::{JokerRule, RuleSet, ScorecardState};
 use yahtzee_solver::{MockScorecardMemo, dice_dp};
 
 fn profile_dice_dp(n_runs: u32) {
     for _ in 0..n_runs {
         let scorecard_state = ScorecardState::default();
         let scorecard_memo = MockScorecardMemo();
-        let joker_rule = JokerRule::FreeChoice;
-        dice_dp(&scorecard_state, &scorecard_memo, joker_rule);
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        dice_dp(&scorecard_state, &scorecard_memo, &rule_set);
     }
 }
 
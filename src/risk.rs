@@ -0,0 +1,398 @@
+use crate::advisor::{Advice, TurnSolution};
+use crate::types::{Action, DiceState, RuleSet, ScorecardState};
+use crate::{
+    DpValue, MapMemo, Memo, VecMemo, dice_dp_with_value, downstream_transitions, first_roll_value,
+};
+
+/// What a risk-sensitive policy maximises, in terms of X, the points scored for the rest of the
+/// game. A positive lambda is risk-averse, a negative lambda is risk-seeking, and a lambda of 0 is
+/// the same as maximising the EV.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RiskObjective {
+    /// E[X] - lambda * Var[X]. This is not time-consistent, so each decision maximises the
+    /// objective of the rest of the game from that decision, assuming it is played the same way.
+    MeanVariance { lambda: f64 },
+    /// The certainty equivalent -ln(E[exp(-lambda * X)]) / lambda, which orders policies the same
+    /// way as the exponential utility E[-exp(-lambda * X)].
+    ExponentialUtility { lambda: f64 },
+}
+
+/// The value of a state under a risk-sensitive policy. We track the first two moments of the
+/// points scored for the rest of the game under any objective, so that the mean and variance of
+/// every policy are known.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RiskValue {
+    objective: RiskObjective,
+    mean: f64,
+    second_moment: f64,
+    /// The certainty equivalent under the exponential lambda, or the mean otherwise.
+    certainty_equivalent: f64,
+}
+
+/// Maximises a RiskObjective instead of the expected score. The scorecard table only holds EVs, so
+/// the value of each state under the objective is solved when first needed.
+pub struct RiskSolver {
+    rule_set: RuleSet,
+    objective: RiskObjective,
+    memo: MapMemo<ScorecardState, RiskValue>,
+    turn_memo: MapMemo<ScorecardState, TurnSolution<RiskValue>>,
+}
+
+impl RiskObjective {
+    /// The lambda of the exponential utility, or 0 if it isn't one.
+    fn exponential_lambda(&self) -> f64 {
+        match *self {
+            RiskObjective::MeanVariance { .. } => 0f64,
+            RiskObjective::ExponentialUtility { lambda } => lambda,
+        }
+    }
+
+    /// The objective of X which orders policies the same way as this objective of weight * X.
+    pub(crate) fn scaled(&self, weight: f64) -> Self {
+        match *self {
+            RiskObjective::MeanVariance { lambda } => RiskObjective::MeanVariance {
+                lambda: lambda * weight,
+            },
+            RiskObjective::ExponentialUtility { lambda } => RiskObjective::ExponentialUtility {
+                lambda: lambda * weight,
+            },
+        }
+    }
+}
+
+impl RiskValue {
+    /// The value of scoring no more points.
+    pub fn zero(objective: RiskObjective) -> Self {
+        Self {
+            objective,
+            mean: 0f64,
+            second_moment: 0f64,
+            certainty_equivalent: 0f64,
+        }
+    }
+
+    pub fn objective(&self) -> RiskObjective {
+        self.objective
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub fn variance(&self) -> f64 {
+        // rounding can leave this just below 0 when there is no variance.
+        (self.second_moment - self.mean * self.mean).max(0f64)
+    }
+
+    /// The value of the objective, which the policy maximises.
+    pub fn objective_value(&self) -> f64 {
+        match self.objective {
+            RiskObjective::MeanVariance { lambda } => self.mean - lambda * self.variance(),
+            RiskObjective::ExponentialUtility { .. } => self.certainty_equivalent,
+        }
+    }
+
+    /// The value of weight * X, where this is the value of X under `objective.scaled(weight)`.
+    ///
+    /// * `weight` - What the points are multiplied by.
+    /// * `objective` - The objective the weighted points are judged by.
+    pub(crate) fn weighted(&self, weight: f64, objective: RiskObjective) -> Self {
+        Self {
+            objective,
+            mean: weight * self.mean,
+            second_moment: weight * weight * self.second_moment,
+            certainty_equivalent: weight * self.certainty_equivalent,
+        }
+    }
+
+    /// The value of the sum of independent points, each with the same objective. Means, variances
+    /// and certainty equivalents all add up.
+    pub(crate) fn independent_sum<'a, I: IntoIterator<Item = &'a Self>>(values: I) -> Self {
+        let mut values = values.into_iter().peekable();
+        let objective = values
+            .peek()
+            .expect("There is always at least one value.")
+            .objective;
+        let mut result = Self::zero(objective);
+        let mut variance = 0f64;
+        for value in values {
+            result.mean += value.mean;
+            variance += value.variance();
+            result.certainty_equivalent += value.certainty_equivalent;
+        }
+        result.second_moment = variance + result.mean * result.mean;
+        result
+    }
+
+    /// This value, with `points` more points scored immediately. Unlike add_score, the points may
+    /// be fractional or past u8.
+    pub(crate) fn add_points(&self, points: f64) -> Self {
+        Self {
+            objective: self.objective,
+            mean: points + self.mean,
+            second_moment: points * points + 2f64 * points * self.mean + self.second_moment,
+            certainty_equivalent: points + self.certainty_equivalent,
+        }
+    }
+}
+
+impl DpValue for RiskValue {
+    type Probability = f64;
+
+    fn expectation<'a, I: IntoIterator<Item = (f64, &'a Self)>>(outcomes: I) -> Self {
+        let mut outcomes = outcomes.into_iter().peekable();
+        let objective = outcomes
+            .peek()
+            .expect("There is always at least one outcome.")
+            .1
+            .objective;
+        let lambda = objective.exponential_lambda();
+        let mut result = Self::zero(objective);
+        // E[exp(-lambda * X)] overflows for all but tiny lambdas, so we keep it as
+        // exp(max_exponent) * scaled_sum, rescaling whenever a larger exponent turns up.
+        let mut max_exponent = f64::NEG_INFINITY;
+        let mut scaled_sum = 0f64;
+        for (p, value) in outcomes {
+            result.mean += p * value.mean;
+            result.second_moment += p * value.second_moment;
+            if lambda == 0f64 {
+                result.certainty_equivalent += p * value.certainty_equivalent;
+                continue;
+            }
+            let exponent = -lambda * value.certainty_equivalent;
+            if exponent > max_exponent {
+                scaled_sum = scaled_sum * (max_exponent - exponent).exp() + p;
+                max_exponent = exponent;
+            } else {
+                scaled_sum += p * (exponent - max_exponent).exp();
+            }
+        }
+        if lambda != 0f64 {
+            result.certainty_equivalent = -(max_exponent + scaled_sum.ln()) / lambda;
+        }
+        result
+    }
+
+    fn is_better(&self, other: &Self) -> bool {
+        self.objective_value() > other.objective_value()
+    }
+
+    fn add_score(&self, points: u8) -> Self {
+        self.add_points(points as f64)
+    }
+}
+
+impl RiskSolver {
+    /// * `rule_set` - The rules to score rolls with.
+    /// * `objective` - What the policy maximises.
+    pub fn new(rule_set: RuleSet, objective: RiskObjective) -> Self {
+        Self {
+            rule_set,
+            objective,
+            memo: MapMemo::default(),
+            turn_memo: MapMemo::default(),
+        }
+    }
+
+    pub fn objective(&self) -> RiskObjective {
+        self.objective
+    }
+
+    /// The value of the rest of the game before the first roll of a turn, when playing to
+    /// maximise the objective.
+    pub fn value(&mut self, scorecard_state: &ScorecardState) -> RiskValue {
+        if scorecard_state.is_terminal() {
+            return RiskValue::zero(self.objective);
+        }
+        if let Some(&value) = self.memo.get(scorecard_state) {
+            return value;
+        }
+        self.solve_downstream(scorecard_state);
+        let (dice_value_memo, _) = self.dice_dp(scorecard_state);
+        let value = first_roll_value(&dice_value_memo, &self.rule_set.dice, self.rule_set.rerolls);
+        self.memo.set(*scorecard_state, value);
+        value
+    }
+
+    /// The action which maximises the objective from the given decision point, or None if the
+    /// game is over. The `ev` of the advice is the mean of the rest of the game when following
+    /// this policy.
+    ///
+    /// * `scorecard_state` - The current scorecard.
+    /// * `dice_state` - The current dice and the number of rerolls left this turn.
+    pub fn advise(
+        &mut self,
+        scorecard_state: &ScorecardState,
+        dice_state: &DiceState,
+    ) -> Option<Advice> {
+        self.advise_value(scorecard_state, dice_state)
+            .map(|(action, value)| Advice {
+                action,
+                ev: value.mean,
+            })
+    }
+
+    /// The same as advise, but with the full value of the rest of the game rather than its mean.
+    pub fn advise_value(
+        &mut self,
+        scorecard_state: &ScorecardState,
+        dice_state: &DiceState,
+    ) -> Option<(Action, RiskValue)> {
+        if scorecard_state.is_terminal() {
+            return None;
+        }
+        if self.turn_memo.get(scorecard_state).is_none() {
+            self.solve_downstream(scorecard_state);
+            let (ev_memo, policy_memo) = self.dice_dp(scorecard_state);
+            self.turn_memo.set(
+                *scorecard_state,
+                TurnSolution {
+                    ev_memo,
+                    policy_memo,
+                },
+            );
+        }
+        let turn_solution = self.turn_memo.get(scorecard_state).unwrap();
+        Some((
+            *turn_solution
+                .policy_memo
+                .get(dice_state)
+                .expect("dice_dp has an action for every dice state of a non-terminal state."),
+            *turn_solution
+                .ev_memo
+                .get(dice_state)
+                .expect("dice_dp fills in every dice state."),
+        ))
+    }
+
+    /// The dice DP of the objective. Requires every downstream scorecard state to be solved.
+    fn dice_dp(
+        &self,
+        scorecard_state: &ScorecardState,
+    ) -> (VecMemo<DiceState, RiskValue>, VecMemo<DiceState, Action>) {
+        dice_dp_with_value(
+            scorecard_state,
+            &self.rule_set,
+            |target_scorecard_state, points| {
+                let transition_value = if target_scorecard_state.is_terminal() {
+                    RiskValue::zero(self.objective)
+                } else {
+                    *self
+                        .memo
+                        .get(target_scorecard_state)
+                        .expect("Every downstream scorecard state must be solved first.")
+                };
+                transition_value.add_score(points)
+            },
+        )
+    }
+
+    /// Solves every scorecard state that can follow a scoring from this one.
+    fn solve_downstream(&mut self, scorecard_state: &ScorecardState) {
+        let rule_set = self.rule_set;
+        for (target_scorecard_state, _) in downstream_transitions(scorecard_state, &rule_set) {
+            self.value(&target_scorecard_state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distribution::score_distribution;
+    use crate::types::JokerRule;
+    use crate::types::{KeepCounts, RollCounts, RollsLeft, ScoreCategory};
+    use crate::{all_filled_except, scorecard_state_ev};
+
+    fn low_straight_dice() -> DiceState {
+        DiceState {
+            roll_counts: RollCounts::try_from([1, 1, 1, 1, 1, 0]).unwrap(),
+            rolls_left: RollsLeft::try_from(1).unwrap(),
+        }
+    }
+
+    #[test]
+    fn zero_lambda_matches_ev() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let scorecard_state = all_filled_except(&rule_set, &[ScoreCategory::Chance]);
+        let memo: VecMemo<ScorecardState, f64> = VecMemo::new();
+        let ev = scorecard_state_ev(&scorecard_state, &memo, &rule_set);
+        let distribution = score_distribution(&scorecard_state, 0, &memo, &rule_set);
+        let distribution_variance: f64 = distribution
+            .probabilities()
+            .iter()
+            .enumerate()
+            .map(|(x, p)| p * (x as f64 - distribution.mean()).powi(2))
+            .sum();
+        for objective in [
+            RiskObjective::MeanVariance { lambda: 0.0 },
+            RiskObjective::ExponentialUtility { lambda: 0.0 },
+        ] {
+            let value = RiskSolver::new(rule_set, objective).value(&scorecard_state);
+            assert!((value.mean() - ev).abs() < 1e-9, "{objective:?}");
+            assert!((value.objective_value() - ev).abs() < 1e-9, "{objective:?}");
+            assert!(
+                (value.variance() - distribution_variance).abs() < 1e-6,
+                "Variance {} but the distribution has {distribution_variance}.",
+                value.variance()
+            );
+        }
+    }
+
+    #[test]
+    fn risk_averse_scores_early() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let scorecard_state = all_filled_except(&rule_set, &[ScoreCategory::Chance]);
+        // rerolling the 2 has the best EV of 21.5, but a variance of 35 / 12.
+        let dice_state = DiceState {
+            roll_counts: RollCounts::try_from([0, 1, 1, 1, 1, 1]).unwrap(),
+            rolls_left: RollsLeft::try_from(1).unwrap(),
+        };
+        for objective in [
+            RiskObjective::MeanVariance { lambda: 2.0 },
+            RiskObjective::ExponentialUtility { lambda: 2.0 },
+        ] {
+            let mut solver = RiskSolver::new(rule_set, objective);
+            let advice = solver.advise(&scorecard_state, &dice_state).unwrap();
+            assert_eq!(
+                advice.action,
+                Action::Score(ScoreCategory::Chance),
+                "{objective:?}"
+            );
+            assert_eq!(advice.ev, 20.0);
+        }
+    }
+
+    #[test]
+    fn risk_seeking_rerolls_everything() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let scorecard_state = all_filled_except(&rule_set, &[ScoreCategory::Chance]);
+        let mut solver = RiskSolver::new(rule_set, RiskObjective::MeanVariance { lambda: -1.0 });
+        let (action, value) = solver
+            .advise_value(&scorecard_state, &low_straight_dice())
+            .unwrap();
+        assert_eq!(
+            action,
+            Action::Keep(KeepCounts::try_from([0, 0, 0, 0, 0, 0]).unwrap())
+        );
+        assert!((value.mean() - 17.5).abs() < 1e-9);
+        assert!((value.variance() - 5.0 * 35.0 / 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn exponential_lambda_orders_certainty_equivalents() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let scorecard_state = all_filled_except(&rule_set, &[ScoreCategory::Chance]);
+        let value = |lambda| {
+            RiskSolver::new(rule_set, RiskObjective::ExponentialUtility { lambda })
+                .value(&scorecard_state)
+        };
+        let (averse, neutral, seeking) = (value(0.5), value(0.0), value(-0.5));
+        assert!(averse.objective_value() < neutral.objective_value());
+        assert!(neutral.objective_value() < seeking.objective_value());
+        assert!(averse.variance() < neutral.variance());
+        assert!(neutral.variance() < seeking.variance());
+        // the risk-seeking policy gives up some EV for its variance.
+        assert!(seeking.mean() < neutral.mean());
+    }
+}
@@ -0,0 +1,259 @@
+use crate::advisor::{Advice, TurnSolution};
+use crate::types::{Action, DiceState, RuleSet, ScorecardState};
+use crate::{
+    DpValue, MapMemo, Memo, VecMemo, dice_dp_with_rolls_left, downstream_transitions,
+    first_roll_value,
+};
+
+/// A scorecard state along with the rolls saved from earlier turns, under the Maxi Yatzy rule.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SavedRollsState {
+    pub scorecard_state: ScorecardState,
+    pub saved_rolls: u8,
+}
+
+/// Maximises the EV when rerolls left unused at the end of a turn are saved for later turns. Each
+/// turn has the rule set's rerolls plus every saved roll, and scoring with n rolls left saves
+/// min(n, max_saved_rolls) of them.
+///
+/// The saved rolls multiply the number of states, so only the states a query needs are solved.
+pub struct SavedRollsSolver {
+    rule_set: RuleSet,
+    memo: MapMemo<SavedRollsState, f64>,
+    turn_memo: MapMemo<SavedRollsState, TurnSolution>,
+}
+
+impl SavedRollsSolver {
+    /// * `rule_set` - The rules to score rolls with, including how many rolls can be saved.
+    pub fn new(rule_set: RuleSet) -> Self {
+        rule_set.validate().expect("The rule set must be valid.");
+        Self {
+            rule_set,
+            memo: MapMemo::default(),
+            turn_memo: MapMemo::default(),
+        }
+    }
+
+    /// The EV of the rest of the game before the first roll of a turn.
+    pub fn ev(&mut self, saved_rolls_state: &SavedRollsState) -> f64 {
+        let saved_rolls_state = self.clamp(saved_rolls_state);
+        if saved_rolls_state.scorecard_state.is_terminal() {
+            return 0f64;
+        }
+        if let Some(&ev) = self.memo.get(&saved_rolls_state) {
+            return ev;
+        }
+        self.solve_downstream(&saved_rolls_state);
+        let (dice_ev_memo, _) = self.dice_dp(&saved_rolls_state);
+        let ev = first_roll_value(
+            &dice_ev_memo,
+            &self.rule_set.dice,
+            self.max_rolls_left(&saved_rolls_state),
+        );
+        self.memo.set(saved_rolls_state, ev);
+        ev
+    }
+
+    /// The optimal action from the given decision point, or None if the game is over.
+    ///
+    /// * `saved_rolls_state` - The current scorecard and the rolls saved before this turn.
+    /// * `dice_state` - The current dice and the number of rolls left this turn, including saved
+    ///   rolls.
+    pub fn advise(
+        &mut self,
+        saved_rolls_state: &SavedRollsState,
+        dice_state: &DiceState,
+    ) -> Option<Advice> {
+        let saved_rolls_state = self.clamp(saved_rolls_state);
+        if saved_rolls_state.scorecard_state.is_terminal() {
+            return None;
+        }
+        if self.turn_memo.get(&saved_rolls_state).is_none() {
+            self.solve_downstream(&saved_rolls_state);
+            let (ev_memo, policy_memo) = self.dice_dp(&saved_rolls_state);
+            self.turn_memo.set(
+                saved_rolls_state,
+                TurnSolution {
+                    ev_memo,
+                    policy_memo,
+                },
+            );
+        }
+        let turn_solution = self.turn_memo.get(&saved_rolls_state).unwrap();
+        Some(Advice {
+            action: *turn_solution
+                .policy_memo
+                .get(dice_state)
+                .expect("dice_dp has an action for every dice state of a non-terminal state."),
+            ev: *turn_solution
+                .ev_memo
+                .get(dice_state)
+                .expect("dice_dp fills in every dice state."),
+        })
+    }
+
+    /// No more than max_saved_rolls can ever be saved.
+    fn clamp(&self, saved_rolls_state: &SavedRollsState) -> SavedRollsState {
+        SavedRollsState {
+            scorecard_state: saved_rolls_state.scorecard_state,
+            saved_rolls: saved_rolls_state
+                .saved_rolls
+                .min(self.rule_set.max_saved_rolls),
+        }
+    }
+
+    /// The rolls left after the first roll of a turn.
+    fn max_rolls_left(&self, saved_rolls_state: &SavedRollsState) -> u8 {
+        self.rule_set.rerolls + saved_rolls_state.saved_rolls
+    }
+
+    /// The dice DP of a turn with saved rolls. Requires every downstream SavedRollsState to be
+    /// solved.
+    fn dice_dp(
+        &self,
+        saved_rolls_state: &SavedRollsState,
+    ) -> (VecMemo<DiceState, f64>, VecMemo<DiceState, Action>) {
+        dice_dp_with_rolls_left(
+            &saved_rolls_state.scorecard_state,
+            &self.rule_set,
+            self.max_rolls_left(saved_rolls_state),
+            |target_scorecard_state, points, rolls_left| {
+                let transition_ev = if target_scorecard_state.is_terminal() {
+                    0f64
+                } else {
+                    *self
+                        .memo
+                        .get(&self.clamp(&SavedRollsState {
+                            scorecard_state: *target_scorecard_state,
+                            saved_rolls: rolls_left,
+                        }))
+                        .expect("Every downstream saved rolls state must be solved first.")
+                };
+                transition_ev.add_score(points)
+            },
+        )
+    }
+
+    /// Solves every SavedRollsState that can follow a scoring from this one, with any number of
+    /// rolls saved.
+    fn solve_downstream(&mut self, saved_rolls_state: &SavedRollsState) {
+        let rule_set = self.rule_set;
+        for (target_scorecard_state, _) in
+            downstream_transitions(&saved_rolls_state.scorecard_state, &rule_set)
+        {
+            for saved_rolls in 0..=rule_set.max_saved_rolls {
+                self.ev(&SavedRollsState {
+                    scorecard_state: target_scorecard_state,
+                    saved_rolls,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Dice, RollCounts, RollsLeft, ScoreCategory};
+    use crate::{all_filled_except, scorecard_state_ev};
+
+    fn maxi_rule_set(max_saved_rolls: u8) -> RuleSet {
+        RuleSet {
+            dice: Dice::MAXI,
+            max_saved_rolls,
+            ..RuleSet::yatzy()
+        }
+    }
+
+    #[test]
+    fn no_saved_rolls_matches_ev() {
+        let rule_set = maxi_rule_set(0);
+        let scorecard_state =
+            all_filled_except(&rule_set, &[ScoreCategory::Chance, ScoreCategory::Sixes]);
+        let mut solver = SavedRollsSolver::new(rule_set);
+        let ev = solver.ev(&SavedRollsState {
+            scorecard_state,
+            saved_rolls: 0,
+        });
+        let mut memo: VecMemo<ScorecardState, f64> = VecMemo::new();
+        for (score_category, points) in (0..=6)
+            .map(|n| (ScoreCategory::Sixes, 6 * n))
+            .chain([10, 20, 30].map(|points| (ScoreCategory::Chance, points)))
+        {
+            let one_left = scorecard_state.score(score_category, points).unwrap();
+            memo.set(one_left, scorecard_state_ev(&one_left, &memo, &rule_set));
+        }
+        let expected = scorecard_state_ev(&scorecard_state, &memo, &rule_set);
+        assert!(
+            (ev - expected).abs() < 1e-9,
+            "Got {ev}, expected {expected}."
+        );
+    }
+
+    #[test]
+    fn saved_rolls_are_extra_rerolls() {
+        let rule_set = maxi_rule_set(4);
+        let scorecard_state = all_filled_except(&rule_set, &[ScoreCategory::Chance]);
+        let mut solver = SavedRollsSolver::new(rule_set);
+        // each die is rerolled independently, and is worth 14/3 with three rolls.
+        let ev = solver.ev(&SavedRollsState {
+            scorecard_state,
+            saved_rolls: 0,
+        });
+        assert!((ev - 28.0).abs() < 1e-9, "Got EV {ev}.");
+        // with two rolls saved, each die gets four rerolls.
+        let ev = solver.ev(&SavedRollsState {
+            scorecard_state,
+            saved_rolls: 2,
+        });
+        let mut die_ev = 3.5f64;
+        for _ in 0..4 {
+            die_ev = (1..=6).map(|x| (x as f64).max(die_ev)).sum::<f64>() / 6.0;
+        }
+        assert!((ev - 6.0 * die_ev).abs() < 1e-9, "Got EV {ev}.");
+        // saving more than the maximum is the same as saving the maximum.
+        let capped = solver.ev(&SavedRollsState {
+            scorecard_state,
+            saved_rolls: 4,
+        });
+        let over = solver.ev(&SavedRollsState {
+            scorecard_state,
+            saved_rolls: 7,
+        });
+        assert_eq!(capped, over);
+    }
+
+    #[test]
+    fn advice_counts_saved_rolls() {
+        let rule_set = maxi_rule_set(2);
+        let scorecard_state =
+            all_filled_except(&rule_set, &[ScoreCategory::Chance, ScoreCategory::Sixes]);
+        let mut solver = SavedRollsSolver::new(rule_set);
+        let saved_rolls_state = SavedRollsState {
+            scorecard_state,
+            saved_rolls: 0,
+        };
+        // six sixes are scored as sixes straight away, saving both rerolls for chance.
+        let dice_state = DiceState {
+            roll_counts: RollCounts::new([0, 0, 0, 0, 0, 6], Dice::MAXI).unwrap(),
+            rolls_left: RollsLeft::try_from(2).unwrap(),
+        };
+        let advice = solver.advise(&saved_rolls_state, &dice_state).unwrap();
+        assert_eq!(advice.action, Action::Score(ScoreCategory::Sixes));
+        let chance_only = scorecard_state.score(ScoreCategory::Sixes, 36).unwrap();
+        let expected = 36.0
+            + solver.ev(&SavedRollsState {
+                scorecard_state: chance_only,
+                saved_rolls: 2,
+            });
+        assert_eq!(advice.ev, expected);
+        assert!(
+            expected
+                > 36.0
+                    + solver.ev(&SavedRollsState {
+                        scorecard_state: chance_only,
+                        saved_rolls: 0,
+                    })
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use crate::types::{RollCounts, RuleSet, ScoreCategory, ScorecardState, ScoringError};
+use enum_map::EnumMap;
+use strum::IntoEnumIterator;
+
+/// The scorecard of a real game, which records the points in each box and the bonuses. The solver
+/// only needs the ScorecardState, which forgets the points, but a player wants to see their total.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Scorecard {
+    rule_set: RuleSet,
+    /// The points scored in each box, or None if the box is open or outside the variant.
+    points: EnumMap<ScoreCategory, Option<u8>>,
+    yahtzee_bonus_count: u8,
+}
+
+impl Scorecard {
+    /// An empty scorecard.
+    ///
+    /// * `rule_set` - The rules the game is played under, which must pass `RuleSet::validate`.
+    pub fn new(rule_set: &RuleSet) -> Self {
+        rule_set.validate().expect("The rule set must be valid.");
+        Self {
+            rule_set: *rule_set,
+            points: EnumMap::default(),
+            yahtzee_bonus_count: 0,
+        }
+    }
+
+    pub fn rule_set(&self) -> &RuleSet {
+        &self.rule_set
+    }
+
+    /// The points scored in the box, or None if it is open or outside the variant.
+    pub fn points(&self, category: ScoreCategory) -> Option<u8> {
+        self.points[category]
+    }
+
+    /// The number of bonus yahtzees scored after the yahtzee box.
+    pub fn yahtzee_bonus_count(&self) -> u8 {
+        self.yahtzee_bonus_count
+    }
+
+    pub fn yahtzee_bonus(&self) -> u16 {
+        self.yahtzee_bonus_count as u16 * self.rule_set.yahtzee_bonus_value as u16
+    }
+
+    /// The uncapped sum of the upper section boxes.
+    pub fn upper_section_score(&self) -> u16 {
+        self.section_score(ScoreCategory::is_upper_section)
+    }
+
+    pub fn upper_section_bonus(&self) -> u16 {
+        if self.upper_section_score() >= self.rule_set.upper_section_bonus_threshold as u16 {
+            self.rule_set.upper_section_bonus_value as u16
+        } else {
+            0
+        }
+    }
+
+    pub fn lower_section_score(&self) -> u16 {
+        self.section_score(ScoreCategory::is_lower_section)
+    }
+
+    /// The grand total of every box and bonus.
+    pub fn total(&self) -> u16 {
+        self.upper_section_score()
+            + self.upper_section_bonus()
+            + self.lower_section_score()
+            + self.yahtzee_bonus()
+    }
+
+    /// Whether every box of the variant is filled.
+    pub fn is_complete(&self) -> bool {
+        self.scorecard_state().is_terminal()
+    }
+
+    /// The state of the scorecard as far as the solver is concerned. This is lossless, in that a
+    /// scorecard always has the same EV as its state.
+    pub fn scorecard_state(&self) -> ScorecardState {
+        let mut scorecard_state = ScorecardState::new_game(&self.rule_set);
+        // capped addition saturates, so the boxes can be scored in any order.
+        for (category, points) in self.points.iter() {
+            if let Some(points) = *points {
+                scorecard_state = scorecard_state
+                    .score(category, points)
+                    .expect("Each box of the variant is only filled once.");
+            }
+        }
+        scorecard_state
+    }
+
+    /// Scores the roll in the box, returning (category_score, bonus_score) as
+    /// `ScorecardState::score_value` does. The scorecard is unchanged if the move is invalid.
+    ///
+    /// * `roll` - The dice at the end of the turn.
+    /// * `category` - The box to score them in.
+    pub fn score(
+        &mut self,
+        roll: &RollCounts,
+        category: ScoreCategory,
+    ) -> Result<(u8, u8), ScoringError> {
+        let (category_score, bonus_score) =
+            self.scorecard_state()
+                .score_value(roll, category, &self.rule_set)?;
+        let upper_section_bonus = self.upper_section_bonus();
+        self.points[category] = Some(category_score);
+        // whatever bonus isn't the upper section bonus is for a bonus yahtzee.
+        let upper_section_bonus = (self.upper_section_bonus() - upper_section_bonus) as u8;
+        if bonus_score > upper_section_bonus {
+            self.yahtzee_bonus_count += 1;
+        }
+        Ok((category_score, bonus_score))
+    }
+
+    fn section_score(&self, in_section: fn(&ScoreCategory) -> bool) -> u16 {
+        ScoreCategory::iter()
+            .filter(in_section)
+            .filter_map(|x| self.points[x])
+            .map(u16::from)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CappedUpperSectionScore, Dice, JokerRule};
+
+    #[test]
+    fn totals_and_bonuses() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let mut scorecard = Scorecard::new(&rule_set);
+        let yahtzee = RollCounts::try_from([0, 0, 0, 0, 0, 5]).unwrap();
+        assert_eq!(
+            scorecard.score(&yahtzee, ScoreCategory::Yahtzee),
+            Ok((50, 0))
+        );
+        assert_eq!(
+            scorecard.score(&yahtzee, ScoreCategory::Sixes),
+            Ok((30, 100))
+        );
+        let fives = RollCounts::try_from([0, 0, 0, 0, 5, 0]).unwrap();
+        assert_eq!(scorecard.score(&fives, ScoreCategory::Fives), Ok((25, 100)));
+        assert_eq!(scorecard.yahtzee_bonus_count(), 2);
+        assert_eq!(scorecard.upper_section_bonus(), 0);
+        let fours = RollCounts::try_from([1, 0, 0, 4, 0, 0]).unwrap();
+        // 71 is past the threshold, and past the cap of the scorecard state.
+        assert_eq!(scorecard.score(&fours, ScoreCategory::Fours), Ok((16, 35)));
+        assert_eq!(scorecard.yahtzee_bonus_count(), 2);
+        assert_eq!(scorecard.upper_section_score(), 71);
+        assert_eq!(scorecard.upper_section_bonus(), 35);
+        assert_eq!(scorecard.lower_section_score(), 50);
+        assert_eq!(scorecard.total(), 71 + 35 + 50 + 200);
+        assert_eq!(
+            scorecard
+                .scorecard_state()
+                .capped_upper_section_score
+                .score(),
+            CappedUpperSectionScore::STANDARD_CAP
+        );
+    }
+
+    #[test]
+    fn invalid_move_leaves_scorecard_unchanged() {
+        let rule_set = RuleSet::standard(JokerRule::Forced);
+        let mut scorecard = Scorecard::new(&rule_set);
+        let yahtzee = RollCounts::try_from([0, 0, 5, 0, 0, 0]).unwrap();
+        scorecard.score(&yahtzee, ScoreCategory::Yahtzee).unwrap();
+        let before = scorecard.clone();
+        // the forced joker rule requires the open threes box.
+        assert_eq!(
+            scorecard.score(&yahtzee, ScoreCategory::Chance),
+            Err(ScoringError::InvalidScoreCategory {
+                category: ScoreCategory::Chance
+            })
+        );
+        assert_eq!(
+            scorecard.score(&yahtzee, ScoreCategory::Yahtzee),
+            Err(ScoringError::InvalidScoreCategory {
+                category: ScoreCategory::Yahtzee
+            })
+        );
+        assert_eq!(scorecard, before);
+    }
+
+    #[test]
+    fn scorecard_state_matches_scoring() {
+        for rule_set in [
+            RuleSet::standard(JokerRule::Forced),
+            RuleSet::yatzy(),
+            RuleSet::yacht(),
+            RuleSet {
+                dice: Dice::MAXI,
+                upper_section_bonus_threshold: 84,
+                ..RuleSet::yatzy()
+            },
+        ] {
+            let mut scorecard = Scorecard::new(&rule_set);
+            let mut scorecard_state = ScorecardState::new_game(&rule_set);
+            assert_eq!(scorecard.scorecard_state(), scorecard_state);
+            // fill the boxes in order with whatever rolls come next.
+            let mut rolls = rule_set.dice.rolls().iter().cycle();
+            while !scorecard_state.is_terminal() {
+                let roll = rolls.next().unwrap();
+                let Some(&category) = scorecard_state
+                    .valid_score_categories(roll, &rule_set)
+                    .first()
+                else {
+                    continue;
+                };
+                let (category_score, _) = scorecard.score(roll, category).unwrap();
+                scorecard_state = scorecard_state.score(category, category_score).unwrap();
+                assert_eq!(scorecard.scorecard_state(), scorecard_state);
+                assert_eq!(scorecard.points(category), Some(category_score));
+            }
+            assert!(scorecard.is_complete());
+        }
+    }
+}
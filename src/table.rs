@@ -0,0 +1,469 @@
+use crate::types::{Dice, JokerRule, RuleSet, ScorecardState, Variant};
+use crate::{IndexKey, Memo};
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Identifies a solved table file.
+const MAGIC: [u8; 8] = *b"YZTABLE\0";
+/// Bumped whenever the layout of the file changes.
+const FORMAT_VERSION: u32 = 1;
+/// The indexing scheme of `IndexKey for ScorecardState`. Bumped whenever it changes, as the values
+/// of old files would then be attributed to the wrong states.
+const INDEX_SCHEME: u32 = 1;
+/// Values are stored as little-endian f64, with NaN for states which were not solved.
+const VALUE_TYPE_F64_LE: u32 = 1;
+/// The joker rule as a u32, then the other fields of the RuleSet as one byte each, padded with
+/// zeros.
+const RULE_SET_LEN: usize = 4 + 16;
+/// magic, version, index scheme, value type, rule set, number of values and checksum. This is a
+/// multiple of 8 bytes so that the values of a memory mapped file are aligned.
+const HEADER_LEN: usize = 8 + 4 + 4 + 4 + RULE_SET_LEN + 8 + 8;
+
+/// A solved ScorecardState -> EV table, loaded from a file. This is read-only: `set` and `remove`
+/// panic.
+pub struct ScorecardTable {
+    rule_set: RuleSet,
+    values: TableValues,
+}
+
+enum TableValues {
+    Owned(Vec<f64>),
+    Mapped(Mmap),
+}
+
+#[derive(Debug)]
+pub enum TableError {
+    Io(std::io::Error),
+    InvalidMagic,
+    UnsupportedVersion { got: u32 },
+    UnknownJokerRule { got: u32 },
+    UnknownVariant { got: u8 },
+    InvalidDice { num_dice: u8, num_faces: u8 },
+    RuleMismatch { expected: RuleSet, got: RuleSet },
+    IndexSchemeMismatch { expected: u32, got: u32 },
+    ValueTypeMismatch { expected: u32, got: u32 },
+    NumValuesMismatch { expected: u64, got: u64 },
+    LengthMismatch { expected: u64, got: u64 },
+    ChecksumMismatch { expected: u64, got: u64 },
+}
+
+impl From<std::io::Error> for TableError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl ScorecardTable {
+    /// Reads a table from a file into memory.
+    ///
+    /// * `path` - The file written by `save_table`.
+    /// * `rule_set` - The rules the caller expects the table to have been solved with.
+    pub fn load<P: AsRef<Path>>(path: P, rule_set: &RuleSet) -> Result<Self, TableError> {
+        let mut bytes = Vec::new();
+        BufReader::new(File::open(path)?).read_to_end(&mut bytes)?;
+        let values = parse(&bytes, rule_set)?
+            .chunks_exact(8)
+            .map(|x| f64::from_le_bytes(x.try_into().unwrap()))
+            .collect();
+        Ok(Self {
+            rule_set: *rule_set,
+            values: TableValues::Owned(values),
+        })
+    }
+
+    /// Memory maps a table read-only, so that several processes can share one copy of it. The
+    /// whole file is still read once to verify the checksum.
+    ///
+    /// The file must not be modified while it is mapped.
+    ///
+    /// * `path` - The file written by `save_table`.
+    /// * `rule_set` - The rules the caller expects the table to have been solved with.
+    pub fn load_mmap<P: AsRef<Path>>(path: P, rule_set: &RuleSet) -> Result<Self, TableError> {
+        if cfg!(target_endian = "big") {
+            return Self::load(path, rule_set);
+        }
+        let file = File::open(path)?;
+        // SAFETY: the map is read-only, and we document that the file must not be modified while
+        // it is mapped.
+        let mmap = unsafe { Mmap::map(&file)? };
+        parse(&mmap, rule_set)?;
+        Ok(Self {
+            rule_set: *rule_set,
+            values: TableValues::Mapped(mmap),
+        })
+    }
+
+    pub fn rule_set(&self) -> &RuleSet {
+        &self.rule_set
+    }
+
+    fn values(&self) -> &[f64] {
+        match &self.values {
+            TableValues::Owned(values) => values,
+            TableValues::Mapped(mmap) => {
+                // SAFETY: parse has checked the length, the header is a multiple of 8 bytes long
+                // and the map is page aligned, so the values are aligned f64s. They are little
+                // endian, which matches the target as load_mmap falls back to load otherwise.
+                let (prefix, values, suffix) = unsafe { mmap[HEADER_LEN..].align_to::<f64>() };
+                debug_assert!(prefix.is_empty() && suffix.is_empty());
+                values
+            }
+        }
+    }
+}
+
+impl Memo<ScorecardState, f64> for ScorecardTable {
+    fn get(&self, key: &ScorecardState) -> Option<&f64> {
+        // states of another variant or cap are not in the table, and may index past its end.
+        if key.variant() != self.rule_set.variant
+            || key.capped_upper_section_score.cap() != self.rule_set.upper_section_cap()
+        {
+            return None;
+        }
+        let value = self.values().get(key.to_index())?;
+        if value.is_nan() { None } else { Some(value) }
+    }
+
+    fn set(&mut self, _key: ScorecardState, _value: f64) -> Option<f64> {
+        panic!("ScorecardTable is read-only.");
+    }
+
+    fn remove(&mut self, _key: &ScorecardState) -> Option<f64> {
+        panic!("ScorecardTable is read-only.");
+    }
+}
+
+/// Writes a solved ScorecardState -> EV memo to a file. States missing from the memo (terminal
+/// and unreachable states) are stored as NaN, and read back as missing.
+///
+/// * `memo` - The memo returned by scorecard_dp.
+/// * `rule_set` - The rules the memo was solved with.
+/// * `path` - The file to write.
+pub fn save_table<M: Memo<ScorecardState, f64>, P: AsRef<Path>>(
+    memo: &M,
+    rule_set: &RuleSet,
+    path: P,
+) -> Result<(), TableError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_table(memo, rule_set, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// The same as save_table, but writes to any writer.
+pub fn write_table<M: Memo<ScorecardState, f64>, W: Write>(
+    memo: &M,
+    rule_set: &RuleSet,
+    writer: &mut W,
+) -> Result<(), TableError> {
+    let num_values = ScorecardState::max_index_for(rule_set) + 1;
+    let mut values = Vec::with_capacity(num_values * 8);
+    for index in 0..num_values {
+        let value = memo
+            .get(&ScorecardState::from_index(index, rule_set))
+            .copied()
+            .unwrap_or(f64::NAN);
+        values.extend_from_slice(&value.to_le_bytes());
+    }
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    writer.write_all(&INDEX_SCHEME.to_le_bytes())?;
+    writer.write_all(&VALUE_TYPE_F64_LE.to_le_bytes())?;
+    writer.write_all(&encode_rule_set(rule_set))?;
+    writer.write_all(&(num_values as u64).to_le_bytes())?;
+    writer.write_all(&checksum(&values).to_le_bytes())?;
+    writer.write_all(&values)?;
+    Ok(())
+}
+
+/// Validates the header and checksum of a table file, returning the bytes of its values. A header
+/// with the wrong number of values is a NumValuesMismatch, while a file which doesn't hold as many
+/// bytes of values as its header says (e.g. it was truncated) is a LengthMismatch.
+fn parse<'a>(bytes: &'a [u8], rule_set: &RuleSet) -> Result<&'a [u8], TableError> {
+    if bytes.len() < HEADER_LEN || bytes[0..8] != MAGIC {
+        return Err(TableError::InvalidMagic);
+    }
+    let u32_at = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    let u64_at = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+    let version = u32_at(8);
+    if version != FORMAT_VERSION {
+        return Err(TableError::UnsupportedVersion { got: version });
+    }
+    let index_scheme = u32_at(12);
+    if index_scheme != INDEX_SCHEME {
+        return Err(TableError::IndexSchemeMismatch {
+            expected: INDEX_SCHEME,
+            got: index_scheme,
+        });
+    }
+    let value_type = u32_at(16);
+    if value_type != VALUE_TYPE_F64_LE {
+        return Err(TableError::ValueTypeMismatch {
+            expected: VALUE_TYPE_F64_LE,
+            got: value_type,
+        });
+    }
+    let file_rule_set = decode_rule_set(&bytes[20..20 + RULE_SET_LEN])?;
+    if file_rule_set != *rule_set {
+        return Err(TableError::RuleMismatch {
+            expected: *rule_set,
+            got: file_rule_set,
+        });
+    }
+    let num_values = u64_at(20 + RULE_SET_LEN);
+    let expected_num_values = (ScorecardState::max_index_for(rule_set) + 1) as u64;
+    if num_values != expected_num_values {
+        return Err(TableError::NumValuesMismatch {
+            expected: expected_num_values,
+            got: num_values,
+        });
+    }
+    let values = &bytes[HEADER_LEN..];
+    if values.len() as u64 != num_values * 8 {
+        return Err(TableError::LengthMismatch {
+            expected: num_values * 8,
+            got: values.len() as u64,
+        });
+    }
+    let expected_checksum = u64_at(28 + RULE_SET_LEN);
+    let got_checksum = checksum(values);
+    if got_checksum != expected_checksum {
+        return Err(TableError::ChecksumMismatch {
+            expected: expected_checksum,
+            got: got_checksum,
+        });
+    }
+    Ok(values)
+}
+
+/// 64 bit FNV-1a, which is plenty to catch truncated or corrupted files.
+fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn encode_rule_set(rule_set: &RuleSet) -> [u8; RULE_SET_LEN] {
+    let mut bytes = [0u8; RULE_SET_LEN];
+    bytes[0..4].copy_from_slice(&joker_rule_to_u32(rule_set.joker_rule).to_le_bytes());
+    bytes[4..16].copy_from_slice(&[
+        rule_set.full_house_value,
+        rule_set.small_straight_value,
+        rule_set.large_straight_value,
+        rule_set.yahtzee_value,
+        rule_set.yahtzee_bonus_value,
+        rule_set.upper_section_bonus_value,
+        rule_set.upper_section_bonus_threshold,
+        variant_to_u8(rule_set.variant),
+        rule_set.dice.num_dice(),
+        rule_set.dice.num_faces(),
+        rule_set.rerolls,
+        rule_set.max_saved_rolls,
+    ]);
+    bytes
+}
+
+fn decode_rule_set(bytes: &[u8]) -> Result<RuleSet, TableError> {
+    Ok(RuleSet {
+        variant: variant_from_u8(bytes[11])?,
+        dice: Dice::new(bytes[12], bytes[13]).map_err(|_| TableError::InvalidDice {
+            num_dice: bytes[12],
+            num_faces: bytes[13],
+        })?,
+        joker_rule: joker_rule_from_u32(u32::from_le_bytes(bytes[0..4].try_into().unwrap()))?,
+        full_house_value: bytes[4],
+        small_straight_value: bytes[5],
+        large_straight_value: bytes[6],
+        yahtzee_value: bytes[7],
+        yahtzee_bonus_value: bytes[8],
+        upper_section_bonus_value: bytes[9],
+        upper_section_bonus_threshold: bytes[10],
+        rerolls: bytes[14],
+        max_saved_rolls: bytes[15],
+    })
+}
+
+fn joker_rule_to_u32(joker_rule: JokerRule) -> u32 {
+    match joker_rule {
+        JokerRule::Forced => 0,
+        JokerRule::FreeChoice => 1,
+        JokerRule::Original => 2,
+        JokerRule::None => 3,
+    }
+}
+
+fn joker_rule_from_u32(value: u32) -> Result<JokerRule, TableError> {
+    match value {
+        0 => Ok(JokerRule::Forced),
+        1 => Ok(JokerRule::FreeChoice),
+        2 => Ok(JokerRule::Original),
+        3 => Ok(JokerRule::None),
+        _ => Err(TableError::UnknownJokerRule { got: value }),
+    }
+}
+
+fn variant_to_u8(variant: Variant) -> u8 {
+    match variant {
+        Variant::Yahtzee => 0,
+        Variant::Yatzy => 1,
+        Variant::Yacht => 2,
+    }
+}
+
+fn variant_from_u8(value: u8) -> Result<Variant, TableError> {
+    match value {
+        0 => Ok(Variant::Yahtzee),
+        1 => Ok(Variant::Yatzy),
+        2 => Ok(Variant::Yacht),
+        _ => Err(TableError::UnknownVariant { got: value }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VecMemo;
+    use crate::types::ScoreCategory;
+
+    fn sample_memo() -> VecMemo<ScorecardState, f64> {
+        let mut memo = VecMemo::new();
+        memo.set(ScorecardState::default(), 254.5896);
+        memo.set(
+            ScorecardState::default()
+                .score(ScoreCategory::Yahtzee, 0)
+                .unwrap(),
+            1.5,
+        );
+        memo
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("yahtzee-solver-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("round_trip");
+        save_table(&sample_memo(), &RuleSet::standard(JokerRule::Forced), &path).unwrap();
+        for table in [
+            ScorecardTable::load(&path, &RuleSet::standard(JokerRule::Forced)).unwrap(),
+            ScorecardTable::load_mmap(&path, &RuleSet::standard(JokerRule::Forced)).unwrap(),
+        ] {
+            assert_eq!(table.get(&ScorecardState::default()), Some(&254.5896));
+            let scratched = ScorecardState::default()
+                .score(ScoreCategory::Yahtzee, 0)
+                .unwrap();
+            assert_eq!(table.get(&scratched), Some(&1.5));
+            let unsolved = ScorecardState::default()
+                .score(ScoreCategory::Aces, 3)
+                .unwrap();
+            assert_eq!(table.get(&unsolved), None);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn table_sized_to_upper_section_cap() {
+        let yacht = RuleSet::yacht();
+        let scorecard_state = ScorecardState::new_game(&yacht);
+        let mut memo = VecMemo::new();
+        memo.set(scorecard_state, 84.0);
+        let mut bytes = Vec::new();
+        write_table(&memo, &yacht, &mut bytes).unwrap();
+        // yacht has no upper section bonus, so only a cap of 0 is stored.
+        assert_eq!(
+            bytes.len(),
+            HEADER_LEN + 8 * (ScorecardState::max_index_for(&yacht) + 1)
+        );
+        let path = temp_path("sized_to_cap");
+        std::fs::write(&path, &bytes).unwrap();
+        let table = ScorecardTable::load(&path, &yacht).unwrap();
+        assert_eq!(table.get(&scorecard_state), Some(&84.0));
+        // a state of another cap is past the end of the table.
+        let maxi = ScorecardState::new_game(&RuleSet {
+            dice: Dice::MAXI,
+            ..RuleSet::yatzy()
+        })
+        .score(ScoreCategory::Sixes, 36)
+        .unwrap();
+        assert_eq!(table.get(&maxi), None);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rule_mismatch_rejected() {
+        let path = temp_path("rule_mismatch");
+        let rule_set = RuleSet::standard(JokerRule::Forced);
+        save_table(&sample_memo(), &rule_set, &path).unwrap();
+        let free_choice = RuleSet::standard(JokerRule::FreeChoice);
+        let result = ScorecardTable::load(&path, &free_choice);
+        assert!(matches!(
+            result,
+            Err(TableError::RuleMismatch { expected, got })
+                if expected == free_choice && got == rule_set
+        ));
+        let no_bonus = RuleSet {
+            yahtzee_bonus_value: 0,
+            ..rule_set
+        };
+        let result = ScorecardTable::load(&path, &no_bonus);
+        assert!(matches!(result, Err(TableError::RuleMismatch { .. })));
+        let maxi = RuleSet {
+            dice: Dice::MAXI,
+            ..rule_set
+        };
+        let result = ScorecardTable::load(&path, &maxi);
+        assert!(matches!(result, Err(TableError::RuleMismatch { .. })));
+        let one_reroll = RuleSet {
+            rerolls: 1,
+            ..rule_set
+        };
+        let result = ScorecardTable::load(&path, &one_reroll);
+        assert!(matches!(result, Err(TableError::RuleMismatch { .. })));
+        assert_eq!(
+            ScorecardTable::load(&path, &rule_set).unwrap().rule_set(),
+            &rule_set
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn corruption_rejected() {
+        let mut bytes = Vec::new();
+        write_table(
+            &sample_memo(),
+            &RuleSet::standard(JokerRule::Forced),
+            &mut bytes,
+        )
+        .unwrap();
+        assert!(parse(&bytes, &RuleSet::standard(JokerRule::Forced)).is_ok());
+        bytes[HEADER_LEN + 3] ^= 1;
+        assert!(matches!(
+            parse(&bytes, &RuleSet::standard(JokerRule::Forced)),
+            Err(TableError::ChecksumMismatch { .. })
+        ));
+        let num_values_at = 20 + RULE_SET_LEN;
+        bytes[num_values_at] ^= 1;
+        assert!(matches!(
+            parse(&bytes, &RuleSet::standard(JokerRule::Forced)),
+            Err(TableError::NumValuesMismatch { .. })
+        ));
+        bytes[num_values_at] ^= 1;
+        bytes.truncate(HEADER_LEN + 8);
+        assert!(matches!(
+            parse(&bytes, &RuleSet::standard(JokerRule::Forced)),
+            Err(TableError::LengthMismatch { expected, got: 8 })
+                if expected == 8 * (ScorecardState::max_index_for(&RuleSet::standard(JokerRule::Forced)) + 1) as u64
+        ));
+        bytes[0] = b'X';
+        assert!(matches!(
+            parse(&bytes, &RuleSet::standard(JokerRule::Forced)),
+            Err(TableError::InvalidMagic)
+        ));
+    }
+}
@@ -0,0 +1,212 @@
+use crate::advisor::{Advice, TurnSolution};
+use crate::types::{Action, DiceState, RuleSet, ScorecardState};
+use crate::{MapMemo, Memo, VecMemo, dice_dp_with_value, downstream_transitions, first_roll_value};
+
+/// A scorecard state along with the points still needed to reach the target score. Once the
+/// target is reached, points_needed is 0.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TargetState {
+    pub scorecard_state: ScorecardState,
+    pub points_needed: u16,
+}
+
+/// Maximises P(final score >= target) instead of the expected score. The value of a TargetState is
+/// the probability of scoring at least points_needed more points over the rest of the game.
+///
+/// There are far too many TargetStates to solve them all up front, so states are solved on demand
+/// and cached, as described by downstream_transitions.
+pub struct TargetSolver {
+    rule_set: RuleSet,
+    memo: MapMemo<TargetState, f64>,
+    turn_memo: MapMemo<TargetState, TurnSolution>,
+}
+
+/// The dice DP of the target objective. The same as dice_dp, except scoring is worth the
+/// probability of reaching the target from the resulting TargetState.
+///
+/// Requires the probability of all downstream TargetStates to be calculated.
+///
+/// * `target_state` - The state to solve the dice DP on.
+/// * `target_memo` - The current memo of TargetState -> probability.
+/// * `rule_set` - The rules to score rolls with.
+pub fn target_dice_dp<S: Memo<TargetState, f64>>(
+    target_state: &TargetState,
+    target_memo: &S,
+    rule_set: &RuleSet,
+) -> (
+    impl Memo<DiceState, f64> + use<S>,
+    impl Memo<DiceState, Action> + use<S>,
+) {
+    solve_target_dice_dp(target_state, target_memo, rule_set)
+}
+
+fn solve_target_dice_dp<S: Memo<TargetState, f64>>(
+    target_state: &TargetState,
+    target_memo: &S,
+    rule_set: &RuleSet,
+) -> (VecMemo<DiceState, f64>, VecMemo<DiceState, Action>) {
+    dice_dp_with_value(
+        &target_state.scorecard_state,
+        rule_set,
+        |target_scorecard_state, points| {
+            let points_needed = target_state.points_needed.saturating_sub(points as u16);
+            if points_needed == 0 {
+                1f64
+            } else if target_scorecard_state.is_terminal() {
+                0f64
+            } else {
+                target_memo
+                    .get(&TargetState {
+                        scorecard_state: *target_scorecard_state,
+                        points_needed,
+                    })
+                    .copied()
+                    .expect("Every downstream target state must be solved first.")
+            }
+        },
+    )
+}
+
+impl TargetSolver {
+    /// * `rule_set` - The rules to score rolls with.
+    pub fn new(rule_set: RuleSet) -> Self {
+        Self {
+            rule_set,
+            memo: MapMemo::default(),
+            turn_memo: MapMemo::default(),
+        }
+    }
+
+    /// The probability of reaching the target before the first roll of a turn, when playing to
+    /// maximise it.
+    pub fn probability(&mut self, target_state: &TargetState) -> f64 {
+        if target_state.points_needed == 0 {
+            return 1f64;
+        }
+        if target_state.scorecard_state.is_terminal() {
+            return 0f64;
+        }
+        if let Some(&p) = self.memo.get(target_state) {
+            return p;
+        }
+        self.solve_downstream(target_state);
+        let (dice_value_memo, _) = target_dice_dp(target_state, &self.memo, &self.rule_set);
+        let p = first_roll_value(&dice_value_memo, &self.rule_set.dice, self.rule_set.rerolls);
+        self.memo.set(*target_state, p);
+        p
+    }
+
+    /// The action which maximises the probability of reaching the target from the given decision
+    /// point, or None if the game is over. The `ev` of the advice is that probability.
+    ///
+    /// * `target_state` - The current scorecard and points still needed.
+    /// * `dice_state` - The current dice and the number of rerolls left this turn.
+    pub fn advise(&mut self, target_state: &TargetState, dice_state: &DiceState) -> Option<Advice> {
+        if target_state.scorecard_state.is_terminal() {
+            return None;
+        }
+        if self.turn_memo.get(target_state).is_none() {
+            self.solve_downstream(target_state);
+            let (ev_memo, policy_memo) =
+                solve_target_dice_dp(target_state, &self.memo, &self.rule_set);
+            self.turn_memo.set(
+                *target_state,
+                TurnSolution {
+                    ev_memo,
+                    policy_memo,
+                },
+            );
+        }
+        let turn_solution = self.turn_memo.get(target_state).unwrap();
+        Some(Advice {
+            action: *turn_solution
+                .policy_memo
+                .get(dice_state)
+                .expect("dice_dp has an action for every dice state of a non-terminal state."),
+            ev: *turn_solution
+                .ev_memo
+                .get(dice_state)
+                .expect("dice_dp fills in every dice state."),
+        })
+    }
+
+    /// Solves every TargetState that can follow a scoring from this one.
+    fn solve_downstream(&mut self, target_state: &TargetState) {
+        let rule_set = self.rule_set;
+        for (target_scorecard_state, points) in
+            downstream_transitions(&target_state.scorecard_state, &rule_set)
+        {
+            self.probability(&TargetState {
+                scorecard_state: target_scorecard_state,
+                points_needed: target_state.points_needed.saturating_sub(points as u16),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::all_filled_except;
+    use crate::distribution::score_distribution;
+    use crate::types::JokerRule;
+    use crate::types::{KeepCounts, RollCounts, RollsLeft, ScoreCategory};
+
+    fn chance_only(rule_set: &RuleSet, points_needed: u16) -> TargetState {
+        TargetState {
+            scorecard_state: all_filled_except(rule_set, &[ScoreCategory::Chance]),
+            points_needed,
+        }
+    }
+
+    #[test]
+    fn chance_only_probabilities() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let mut solver = TargetSolver::new(rule_set);
+        assert_eq!(solver.probability(&chance_only(&rule_set, 0)), 1.0);
+        assert!((solver.probability(&chance_only(&rule_set, 5)) - 1.0).abs() < 1e-12);
+        assert_eq!(solver.probability(&chance_only(&rule_set, 31)), 0.0);
+        // every die must end up a six, and each die gets three attempts.
+        let expected = (1.0 - (5f64 / 6.0).powi(3)).powi(5);
+        let p = solver.probability(&chance_only(&rule_set, 30));
+        assert!(
+            (p - expected).abs() < 1e-12,
+            "Got {p}, expected {expected}."
+        );
+        let mut prev = 1f64;
+        for points_needed in 0..=31 {
+            let p = solver.probability(&chance_only(&rule_set, points_needed));
+            assert!(p <= prev + 1e-12, "Not monotonic at {points_needed}.");
+            prev = p;
+        }
+    }
+
+    #[test]
+    fn beats_ev_policy() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let mut solver = TargetSolver::new(rule_set);
+        let target_state = chance_only(&rule_set, 27);
+        let distribution =
+            score_distribution(&target_state.scorecard_state, 0, &VecMemo::new(), &rule_set);
+        let p = solver.probability(&target_state);
+        assert!(p >= distribution.p_at_least(27) - 1e-12);
+    }
+
+    #[test]
+    fn advise_keeps_sixes() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let mut solver = TargetSolver::new(rule_set);
+        let dice_state = DiceState {
+            roll_counts: RollCounts::try_from([1, 0, 0, 0, 0, 4]).unwrap(),
+            rolls_left: RollsLeft::try_from(1).unwrap(),
+        };
+        let advice = solver
+            .advise(&chance_only(&rule_set, 29), &dice_state)
+            .unwrap();
+        assert_eq!(
+            advice.action,
+            Action::Keep(KeepCounts::try_from([0, 0, 0, 0, 0, 4]).unwrap())
+        );
+        assert!((advice.ev - 1.0 / 3.0).abs() < 1e-12);
+    }
+}
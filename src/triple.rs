@@ -0,0 +1,766 @@
+use crate::advisor::{Policy, TurnSolution};
+use crate::risk::{RiskObjective, RiskSolver, RiskValue};
+use crate::types::{
+    DiceState, KeepCounts, RollCounts, RollsLeft, RuleSet, ScoreCategory, ScorecardState,
+    ScoringError,
+};
+use crate::{
+    DpValue, MapMemo, Memo, VecMemo, dice_state_index, dice_state_memo, first_roll_value, reroll_dp,
+};
+
+/// How many times the points of each column count, bonuses included.
+pub const COLUMN_WEIGHTS: [u8; 3] = [1, 2, 3];
+
+/// A Triple Yahtzee scorecard: three columns of the same categories, each scored as a scorecard of
+/// its own. Each turn scores one box of one column, and the game ends once every column is
+/// filled.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TripleScorecardState {
+    pub columns: [ScorecardState; 3],
+}
+
+/// A decision within a Triple Yahtzee turn: either reroll every die not kept, or end the turn by
+/// scoring a category of a column.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TripleAction {
+    Keep(KeepCounts),
+    Score(usize, ScoreCategory),
+}
+
+/// The best action from a decision point, and the approximate EV of the rest of the game when
+/// following it, weights included. The EV does not include points already scored.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TripleAdvice {
+    pub action: TripleAction,
+    pub ev: f64,
+}
+
+impl TripleScorecardState {
+    /// The empty scorecard of the variant, in every column.
+    pub fn new_game(rule_set: &RuleSet) -> Self {
+        Self {
+            columns: [ScorecardState::new_game(rule_set); 3],
+        }
+    }
+
+    /// Whether every column is filled.
+    pub fn is_terminal(&self) -> bool {
+        self.columns.iter().all(|x| x.is_terminal())
+    }
+
+    /// * `column` - On [0..3].
+    /// * `category` - The category of the column to score.
+    /// * `score` - The points scored in the category, before the column's weight.
+    pub fn score(
+        &self,
+        column: usize,
+        category: ScoreCategory,
+        score: u8,
+    ) -> Result<Self, ScoringError> {
+        let mut columns = self.columns;
+        columns[column] = columns[column].score(category, score)?;
+        Ok(Self { columns })
+    }
+
+    /// Returns (category_score, bonus_score) of scoring the roll in a category of a column, as
+    /// ScorecardState::score_value. Neither includes the column's weight.
+    ///
+    /// * `roll` - The dice to score.
+    /// * `column` - On [0..3].
+    /// * `category` - The category of the column to score.
+    /// * `rule_set` - The rules to score rolls with.
+    pub fn score_value(
+        &self,
+        roll: &RollCounts,
+        column: usize,
+        category: ScoreCategory,
+        rule_set: &RuleSet,
+    ) -> Result<(u8, u8), ScoringError> {
+        self.columns[column].score_value(roll, category, rule_set)
+    }
+}
+
+/// A legal action from a decision point, with the approximate EV of the rest of the game when
+/// taking it, and how much EV is lost against the best action. Weights are included.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TripleRankedAction {
+    pub action: TripleAction,
+    pub ev: f64,
+    pub loss: f64,
+}
+
+/// Gives advice for Triple Yahtzee from the solved table of the single column game. Solving the
+/// game exactly would need every combination of three column states, so instead a
+/// TripleScorecardState is valued as the weighted sum of the EV of each of its columns, as if each
+/// column were played out on its own. Each turn is then solved exactly against that value, which
+/// captures the choice of column: say, a poor roll is better spent on the x1 column.
+///
+/// As with Advisor, the dice DP of each queried state is cached.
+pub struct TripleAdvisor<S: Memo<ScorecardState, f64>> {
+    scorecard_memo: S,
+    rule_set: RuleSet,
+    turn_memo: TurnMemo<f64>,
+    risk_solvers: Vec<RiskSolver>,
+    risk_turn_memos: Vec<(RiskObjective, TurnMemo<RiskValue>)>,
+}
+
+/// The cached dice DP of each queried triple state.
+type TurnMemo<V> = MapMemo<TripleScorecardState, TurnSolution<V, TripleAction>>;
+
+/// The value of the rest of a Triple Yahtzee game, built from the weighted value of each column
+/// played out on its own.
+trait TripleValue: DpValue + Copy {
+    /// The value of the points of every column together.
+    fn sum(columns: &[Self]) -> Self;
+    /// This value, with `points` more points scored immediately. Weighted points can be past u8.
+    fn add_points(&self, points: f64) -> Self;
+}
+
+impl TripleValue for f64 {
+    fn sum(columns: &[Self]) -> Self {
+        columns.iter().sum()
+    }
+
+    fn add_points(&self, points: f64) -> Self {
+        points + self
+    }
+}
+
+/// Columns are taken to be independent, which they are when each is played out on its own.
+impl TripleValue for RiskValue {
+    fn sum(columns: &[Self]) -> Self {
+        RiskValue::independent_sum(columns)
+    }
+
+    fn add_points(&self, points: f64) -> Self {
+        RiskValue::add_points(self, points)
+    }
+}
+
+impl<S: Memo<ScorecardState, f64>> TripleAdvisor<S> {
+    /// * `scorecard_memo` - The solved ScorecardState -> EV table of the single column game, for
+    ///   example a loaded `ScorecardTable`.
+    /// * `rule_set` - The rules the table was solved with.
+    pub fn new(scorecard_memo: S, rule_set: RuleSet) -> Self {
+        Self {
+            scorecard_memo,
+            rule_set,
+            turn_memo: MapMemo::default(),
+            risk_solvers: Vec::new(),
+            risk_turn_memos: Vec::new(),
+        }
+    }
+
+    /// The approximate EV of the rest of the game before the first roll of a turn, weights
+    /// included.
+    pub fn ev(&mut self, triple_state: &TripleScorecardState) -> f64 {
+        if triple_state.is_terminal() {
+            return 0f64;
+        }
+        let rerolls = self.rule_set.rerolls;
+        let dice = self.rule_set.dice;
+        first_roll_value(&self.turn_solution(triple_state).ev_memo, &dice, rerolls)
+    }
+
+    /// The best action from the given decision point, or None if the game is over or the dice
+    /// state has more rerolls left than the rule set allows. Every column state must be
+    /// reachable, as the table only holds reachable states.
+    ///
+    /// * `triple_state` - The current scorecard.
+    /// * `dice_state` - The current dice and the number of rerolls left this turn.
+    pub fn advise(
+        &mut self,
+        triple_state: &TripleScorecardState,
+        dice_state: &DiceState,
+    ) -> Option<TripleAdvice> {
+        if triple_state.is_terminal() || !self.is_valid(dice_state) {
+            return None;
+        }
+        let turn_solution = self.turn_solution(triple_state);
+        Some(TripleAdvice {
+            action: *turn_solution
+                .policy_memo
+                .get(dice_state)
+                .expect("dice_dp has an action for every dice state of a non-terminal state."),
+            ev: *turn_solution
+                .ev_memo
+                .get(dice_state)
+                .expect("dice_dp fills in every dice state."),
+        })
+    }
+
+    /// The same as advise, but following the given policy. A risk-sensitive policy values each
+    /// column by the objective of its weighted points, solved on demand as RiskSolver does, and
+    /// takes the columns to be independent. The `ev` of the advice is always the approximate mean
+    /// of the rest of the game when following that policy.
+    ///
+    /// * `triple_state` - The current scorecard.
+    /// * `dice_state` - The current dice and the number of rerolls left this turn.
+    /// * `policy` - The policy to follow.
+    pub fn advise_with(
+        &mut self,
+        triple_state: &TripleScorecardState,
+        dice_state: &DiceState,
+        policy: Policy,
+    ) -> Option<TripleAdvice> {
+        let objective = match policy {
+            Policy::MaxEv => return self.advise(triple_state, dice_state),
+            Policy::Risk(objective) => objective,
+        };
+        if triple_state.is_terminal() || !self.is_valid(dice_state) {
+            return None;
+        }
+        let turn_solution = self.risk_turn_solution(triple_state, objective);
+        Some(TripleAdvice {
+            action: *turn_solution
+                .policy_memo
+                .get(dice_state)
+                .expect("dice_dp has an action for every dice state of a non-terminal state."),
+            ev: turn_solution
+                .ev_memo
+                .get(dice_state)
+                .expect("dice_dp fills in every dice state.")
+                .mean(),
+        })
+    }
+
+    /// Every legal action from the given decision point, best first, or an empty list if the game
+    /// is over or the dice state has more rerolls left than the rule set allows. Keeps are only
+    /// legal with rerolls left. Every column state must be reachable.
+    ///
+    /// * `triple_state` - The current scorecard.
+    /// * `dice_state` - The current dice and the number of rerolls left this turn.
+    pub fn rank_actions(
+        &mut self,
+        triple_state: &TripleScorecardState,
+        dice_state: &DiceState,
+    ) -> Vec<TripleRankedAction> {
+        if triple_state.is_terminal() || !self.is_valid(dice_state) {
+            return Vec::new();
+        }
+        let roll_counts = &dice_state.roll_counts;
+        let mut column_value =
+            |column, scorecard_state: &ScorecardState| self.column_ev(column, scorecard_state);
+        let column_evs = column_values(triple_state, &mut column_value);
+        let mut actions = score_values(
+            triple_state,
+            roll_counts,
+            &self.rule_set,
+            &column_evs,
+            &mut column_value,
+        );
+        let rolls_left = *dice_state.rolls_left.rolls_left();
+        if rolls_left > 0 {
+            let tables = self.rule_set.dice.tables();
+            let turn_solution = self.turn_solution(triple_state);
+            for &keep_counts in tables.valid_keep_counts(roll_counts) {
+                let ev = f64::expectation(tables.transitions().get(&keep_counts).iter().map(
+                    |&(target_roll_counts_rank, p)| {
+                        let memo_idx = dice_state_index(
+                            roll_counts.dice(),
+                            target_roll_counts_rank,
+                            rolls_left - 1,
+                        );
+                        (
+                            p,
+                            turn_solution
+                                .ev_memo
+                                .raw_get(memo_idx)
+                                .expect("dice_dp fills in every dice state."),
+                        )
+                    },
+                ));
+                actions.push((TripleAction::Keep(keep_counts), ev));
+            }
+        }
+        // stable, so ties keep scoring before keeping, as in dice_dp.
+        actions.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        let best_ev = actions[0].1;
+        actions
+            .into_iter()
+            .map(|(action, ev)| TripleRankedAction {
+                action,
+                ev,
+                loss: best_ev - ev,
+            })
+            .collect()
+    }
+
+    /// Drops every cached dice DP, and every solved risk-sensitive policy.
+    pub fn clear_cache(&mut self) {
+        self.turn_memo = MapMemo::default();
+        self.risk_solvers.clear();
+        self.risk_turn_memos.clear();
+    }
+
+    /// Whether the dice state can occur in a turn of the rule set.
+    fn is_valid(&self, dice_state: &DiceState) -> bool {
+        *dice_state.rolls_left.rolls_left() <= self.rule_set.rerolls
+    }
+
+    /// The cached dice DP of the triple state, solving it first if needed.
+    fn turn_solution(
+        &mut self,
+        triple_state: &TripleScorecardState,
+    ) -> &TurnSolution<f64, TripleAction> {
+        if self.turn_memo.get(triple_state).is_none() {
+            let turn_solution =
+                solve_turn(triple_state, &self.rule_set, |column, scorecard_state| {
+                    self.column_ev(column, scorecard_state)
+                });
+            self.turn_memo.set(*triple_state, turn_solution);
+        }
+        self.turn_memo.get(triple_state).unwrap()
+    }
+
+    /// The cached dice DP of the triple state under the objective, solving it first if needed.
+    fn risk_turn_solution(
+        &mut self,
+        triple_state: &TripleScorecardState,
+        objective: RiskObjective,
+    ) -> &TurnSolution<RiskValue, TripleAction> {
+        let index = match self
+            .risk_turn_memos
+            .iter()
+            .position(|(x, _)| *x == objective)
+        {
+            Some(index) => index,
+            None => {
+                self.risk_turn_memos.push((objective, MapMemo::default()));
+                self.risk_turn_memos.len() - 1
+            }
+        };
+        if self.risk_turn_memos[index].1.get(triple_state).is_none() {
+            let rule_set = self.rule_set;
+            let turn_solution = solve_turn(triple_state, &rule_set, |column, scorecard_state| {
+                // the objective of weight * X orders policies of X as the scaled objective does.
+                let weight = COLUMN_WEIGHTS[column] as f64;
+                self.risk_solver(objective.scaled(weight))
+                    .value(scorecard_state)
+                    .weighted(weight, objective)
+            });
+            self.risk_turn_memos[index]
+                .1
+                .set(*triple_state, turn_solution);
+        }
+        self.risk_turn_memos[index].1.get(triple_state).unwrap()
+    }
+
+    /// The solver of the objective, creating it first if needed.
+    fn risk_solver(&mut self, objective: RiskObjective) -> &mut RiskSolver {
+        let index = match self
+            .risk_solvers
+            .iter()
+            .position(|x| x.objective() == objective)
+        {
+            Some(index) => index,
+            None => {
+                self.risk_solvers
+                    .push(RiskSolver::new(self.rule_set, objective));
+                self.risk_solvers.len() - 1
+            }
+        };
+        &mut self.risk_solvers[index]
+    }
+
+    /// The weighted EV of a column played out on its own.
+    fn column_ev(&self, column: usize, scorecard_state: &ScorecardState) -> f64 {
+        if scorecard_state.is_terminal() {
+            return 0f64;
+        }
+        let ev = self
+            .scorecard_memo
+            .get(scorecard_state)
+            .copied()
+            .expect("The table must hold every reachable scorecard state.");
+        COLUMN_WEIGHTS[column] as f64 * ev
+    }
+}
+
+/// The value of each column of the triple state.
+///
+/// * `column_value` - The weighted value of a column played out on its own.
+fn column_values<V: TripleValue, F: FnMut(usize, &ScorecardState) -> V>(
+    triple_state: &TripleScorecardState,
+    column_value: &mut F,
+) -> [V; 3] {
+    std::array::from_fn(|column| column_value(column, &triple_state.columns[column]))
+}
+
+/// Every scoring of the roll, with its weighted points plus the value of every column afterwards.
+///
+/// * `triple_state` - The current scorecard.
+/// * `roll_counts` - The dice to score.
+/// * `rule_set` - The rules to score rolls with.
+/// * `column_values` - The value of each column of the triple state.
+/// * `column_value` - The weighted value of a column played out on its own.
+fn score_values<V: TripleValue, F: FnMut(usize, &ScorecardState) -> V>(
+    triple_state: &TripleScorecardState,
+    roll_counts: &RollCounts,
+    rule_set: &RuleSet,
+    column_values: &[V; 3],
+    column_value: &mut F,
+) -> Vec<(TripleAction, V)> {
+    let mut values = Vec::new();
+    for (column, scorecard_state) in triple_state.columns.iter().enumerate() {
+        for score_category in scorecard_state.valid_score_categories(roll_counts, rule_set) {
+            let (category_score, bonus_score) = scorecard_state
+                .score_value(roll_counts, score_category, rule_set)
+                .expect("We are iterating through valid categories.");
+            let target_scorecard_state = scorecard_state
+                .score(score_category, category_score)
+                .expect("This is a valid score category.");
+            let points =
+                COLUMN_WEIGHTS[column] as f64 * (category_score as f64 + bonus_score as f64);
+            let mut target_column_values = *column_values;
+            target_column_values[column] = column_value(column, &target_scorecard_state);
+            values.push((
+                TripleAction::Score(column, score_category),
+                V::sum(&target_column_values).add_points(points),
+            ));
+        }
+    }
+    values
+}
+
+/// The dice DP of a Triple Yahtzee turn, where scoring is worth its weighted points plus the
+/// value of every column afterwards.
+///
+/// * `triple_state` - The current scorecard.
+/// * `rule_set` - The rules to score rolls with.
+/// * `column_value` - The weighted value of a column played out on its own.
+fn solve_turn<V: TripleValue, F: FnMut(usize, &ScorecardState) -> V>(
+    triple_state: &TripleScorecardState,
+    rule_set: &RuleSet,
+    mut column_value: F,
+) -> TurnSolution<V, TripleAction> {
+    let dice = &rule_set.dice;
+    let mut value_memo: VecMemo<DiceState, V> = dice_state_memo(dice, rule_set.rerolls);
+    let mut policy_memo: VecMemo<DiceState, TripleAction> = dice_state_memo(dice, rule_set.rerolls);
+    let column_values = column_values(triple_state, &mut column_value);
+    for &roll_counts in dice.rolls() {
+        let mut best: Option<(TripleAction, V)> = None;
+        for (action, value) in score_values(
+            triple_state,
+            &roll_counts,
+            rule_set,
+            &column_values,
+            &mut column_value,
+        ) {
+            if best.is_none_or(|(_, best_value)| value.is_better(&best_value)) {
+                best = Some((action, value));
+            }
+        }
+        let Some((best_action, best_value)) = best else {
+            continue;
+        };
+        for raw_rolls_left in 0..=rule_set.rerolls {
+            let dice_state = DiceState {
+                roll_counts,
+                rolls_left: RollsLeft::try_from(raw_rolls_left).unwrap(),
+            };
+            value_memo.set(dice_state.clone(), best_value);
+            policy_memo.set(dice_state, best_action);
+        }
+    }
+    if !triple_state.is_terminal() {
+        reroll_dp(
+            &mut value_memo,
+            &mut policy_memo,
+            dice,
+            rule_set.rerolls,
+            TripleAction::Keep,
+        );
+    }
+    TurnSolution {
+        ev_memo: value_memo,
+        policy_memo,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::advisor::Advisor;
+    use crate::types::{Action, JokerRule};
+    use crate::{all_filled_except, downstream_transitions, scorecard_state_ev};
+
+    /// Chance left in the given columns, and every other column filled.
+    fn chance_only_advisor(
+        open_columns: &[usize],
+    ) -> (
+        TripleAdvisor<VecMemo<ScorecardState, f64>>,
+        TripleScorecardState,
+    ) {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let chance_only = all_filled_except(&rule_set, &[ScoreCategory::Chance]);
+        let mut memo = VecMemo::new();
+        memo.set(
+            chance_only,
+            scorecard_state_ev(&chance_only, &memo, &rule_set),
+        );
+        let mut columns = [all_filled_except(&rule_set, &[]); 3];
+        for &column in open_columns {
+            columns[column] = chance_only;
+        }
+        (
+            TripleAdvisor::new(memo, rule_set),
+            TripleScorecardState { columns },
+        )
+    }
+
+    /// Sixes, full house, yahtzee and chance left, and 14 short of the upper section bonus.
+    fn mid_game_column(rule_set: &RuleSet) -> ScorecardState {
+        let mut scorecard_state = ScorecardState::new_game(rule_set);
+        for (score_category, score) in [
+            (ScoreCategory::Aces, 3),
+            (ScoreCategory::Twos, 6),
+            (ScoreCategory::Threes, 9),
+            (ScoreCategory::Fours, 16),
+            (ScoreCategory::Fives, 15),
+            (ScoreCategory::ThreeOfAKind, 20),
+            (ScoreCategory::FourOfAKind, 0),
+            (ScoreCategory::SmallStraight, 30),
+            (ScoreCategory::LargeStraight, 0),
+        ] {
+            scorecard_state = scorecard_state.score(score_category, score).unwrap();
+        }
+        scorecard_state
+    }
+
+    /// Solves the EV of the scorecard state, and of every state downstream of it.
+    fn solve_ev(
+        scorecard_state: &ScorecardState,
+        memo: &mut MapMemo<ScorecardState, f64>,
+        rule_set: &RuleSet,
+    ) {
+        if scorecard_state.is_terminal() || memo.get(scorecard_state).is_some() {
+            return;
+        }
+        for (target_scorecard_state, _) in downstream_transitions(scorecard_state, rule_set) {
+            solve_ev(&target_scorecard_state, memo, rule_set);
+        }
+        let ev = scorecard_state_ev(scorecard_state, memo, rule_set);
+        memo.set(*scorecard_state, ev);
+    }
+
+    /// The mid-game column in the given column, and every other column filled, with the table of
+    /// the mid-game column.
+    fn mid_game_advisor(
+        column: usize,
+    ) -> (
+        TripleAdvisor<MapMemo<ScorecardState, f64>>,
+        TripleScorecardState,
+        MapMemo<ScorecardState, f64>,
+    ) {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let mid_game_column = mid_game_column(&rule_set);
+        let mut columns = [all_filled_except(&rule_set, &[]); 3];
+        columns[column] = mid_game_column;
+        let mut memo = MapMemo::default();
+        solve_ev(&mid_game_column, &mut memo, &rule_set);
+        (
+            TripleAdvisor::new(memo.clone(), rule_set),
+            TripleScorecardState { columns },
+            memo,
+        )
+    }
+
+    /// The single column action of a triple action in the column.
+    fn column_action(action: TripleAction, column: usize) -> Action {
+        match action {
+            TripleAction::Keep(keep_counts) => Action::Keep(keep_counts),
+            TripleAction::Score(x, score_category) => {
+                assert_eq!(x, column);
+                Action::Score(score_category)
+            }
+        }
+    }
+
+    fn dice_state(roll_counts: [u8; RollCounts::MAX_FACES], rolls_left: u8) -> DiceState {
+        DiceState {
+            roll_counts: RollCounts::try_from(roll_counts).unwrap(),
+            rolls_left: RollsLeft::try_from(rolls_left).unwrap(),
+        }
+    }
+
+    #[test]
+    fn new_game_and_scoring() {
+        let rule_set = RuleSet::default();
+        let triple_state = TripleScorecardState::new_game(&rule_set);
+        assert!(!triple_state.is_terminal());
+        let roll_counts = RollCounts::try_from([0, 0, 0, 2, 3, 0]).unwrap();
+        assert_eq!(
+            triple_state.score_value(&roll_counts, 2, ScoreCategory::FullHouse, &rule_set),
+            Ok((25, 0))
+        );
+        let scored = triple_state.score(2, ScoreCategory::FullHouse, 25).unwrap();
+        assert_eq!(scored.columns[0], triple_state.columns[0]);
+        assert_ne!(scored.columns[2], triple_state.columns[2]);
+        assert_eq!(
+            scored.score(2, ScoreCategory::FullHouse, 25),
+            Err(ScoringError::InvalidScoreCategory {
+                category: ScoreCategory::FullHouse
+            })
+        );
+        let (_, filled) = chance_only_advisor(&[]);
+        assert!(filled.is_terminal());
+    }
+
+    #[test]
+    fn single_column_is_weighted_ev() {
+        for (column, weight) in COLUMN_WEIGHTS.into_iter().enumerate() {
+            let (mut advisor, triple_state) = chance_only_advisor(&[column]);
+            let expected = weight as f64 * 70.0 / 3.0;
+            let ev = advisor.ev(&triple_state);
+            assert!((ev - expected).abs() < 1e-9, "Got EV {ev} in {column}.");
+        }
+    }
+
+    #[test]
+    fn advise_column_by_roll() {
+        let (mut advisor, triple_state) = chance_only_advisor(&[0, 2]);
+        // a high roll is worth the most in the x3 column.
+        let advice = advisor
+            .advise(&triple_state, &dice_state([0, 0, 0, 0, 0, 5], 0))
+            .unwrap();
+        assert_eq!(advice.action, TripleAction::Score(2, ScoreCategory::Chance));
+        assert!((advice.ev - (90.0 + 70.0 / 3.0)).abs() < 1e-9);
+        // and a low roll costs the least in the x1 column.
+        let advice = advisor
+            .advise(&triple_state, &dice_state([5, 0, 0, 0, 0, 0], 0))
+            .unwrap();
+        assert_eq!(advice.action, TripleAction::Score(0, ScoreCategory::Chance));
+        assert!((advice.ev - (5.0 + 70.0)).abs() < 1e-9);
+        let advice = advisor
+            .advise(&triple_state, &dice_state([5, 0, 0, 0, 0, 0], 2))
+            .unwrap();
+        assert!(matches!(advice.action, TripleAction::Keep(_)));
+        // the standard game never has three rerolls left.
+        let too_many_rerolls = dice_state([5, 0, 0, 0, 0, 0], 3);
+        assert_eq!(advisor.advise(&triple_state, &too_many_rerolls), None);
+        assert_eq!(
+            advisor.advise_with(
+                &triple_state,
+                &too_many_rerolls,
+                Policy::Risk(RiskObjective::MeanVariance { lambda: 0.01 })
+            ),
+            None
+        );
+        assert!(
+            advisor
+                .rank_actions(&triple_state, &too_many_rerolls)
+                .is_empty()
+        );
+        let (mut advisor, triple_state) = chance_only_advisor(&[]);
+        assert_eq!(
+            advisor.advise(&triple_state, &dice_state([5, 0, 0, 0, 0, 0], 2)),
+            None
+        );
+    }
+
+    #[test]
+    fn mid_game_column_matches_single_column_game() {
+        // three sixes, with a full house and the upper section bonus in reach.
+        let dice_states = [
+            dice_state([0, 1, 0, 0, 1, 3], 2),
+            dice_state([0, 1, 0, 0, 1, 3], 1),
+            dice_state([0, 0, 2, 0, 0, 3], 0),
+        ];
+        for (column, weight) in COLUMN_WEIGHTS.into_iter().enumerate() {
+            let weight = weight as f64;
+            let (mut triple_advisor, triple_state, memo) = mid_game_advisor(column);
+            let mid_game_column = triple_state.columns[column];
+            let ev = *memo.get(&mid_game_column).unwrap();
+            let mut advisor = Advisor::new(memo, triple_advisor.rule_set);
+            let triple_ev = triple_advisor.ev(&triple_state);
+            assert!(
+                (triple_ev - weight * ev).abs() < 1e-9,
+                "Got EV {triple_ev}."
+            );
+            for dice_state in &dice_states {
+                let ranked_actions = advisor.rank_actions(&mid_game_column, dice_state);
+                let triple_ranked_actions = triple_advisor.rank_actions(&triple_state, dice_state);
+                assert_eq!(triple_ranked_actions.len(), ranked_actions.len());
+                for triple_ranked_action in &triple_ranked_actions {
+                    let action = column_action(triple_ranked_action.action, column);
+                    let ranked_action = ranked_actions.iter().find(|x| x.action == action).unwrap();
+                    assert!((triple_ranked_action.ev - weight * ranked_action.ev).abs() < 1e-9);
+                    assert!((triple_ranked_action.loss - weight * ranked_action.loss).abs() < 1e-9);
+                }
+                let advice = advisor.advise(&mid_game_column, dice_state).unwrap();
+                let triple_advice = triple_advisor.advise(&triple_state, dice_state).unwrap();
+                assert_eq!(column_action(triple_advice.action, column), advice.action);
+                assert!((triple_advice.ev - weight * advice.ev).abs() < 1e-9);
+                assert_eq!(triple_ranked_actions[0].action, triple_advice.action);
+                assert_eq!(
+                    triple_advisor.advise_with(&triple_state, dice_state, Policy::MaxEv),
+                    Some(triple_advice)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn risk_policy_matches_single_column_solver() {
+        let dice_states = [
+            dice_state([0, 1, 0, 0, 1, 3], 2),
+            dice_state([1, 0, 0, 2, 2, 0], 1),
+        ];
+        for objective in [
+            RiskObjective::MeanVariance { lambda: 0.01 },
+            RiskObjective::ExponentialUtility { lambda: 0.05 },
+        ] {
+            for (column, weight) in COLUMN_WEIGHTS.into_iter().enumerate() {
+                let weight = weight as f64;
+                let (mut triple_advisor, triple_state, _) = mid_game_advisor(column);
+                let mid_game_column = triple_state.columns[column];
+                // the weighted points are judged by the objective as the scaled objective judges
+                // the points of the column.
+                let mut solver = RiskSolver::new(triple_advisor.rule_set, objective.scaled(weight));
+                for dice_state in &dice_states {
+                    let advice = solver.advise(&mid_game_column, dice_state).unwrap();
+                    let triple_advice = triple_advisor
+                        .advise_with(&triple_state, dice_state, Policy::Risk(objective))
+                        .unwrap();
+                    assert_eq!(column_action(triple_advice.action, column), advice.action);
+                    assert!((triple_advice.ev - weight * advice.ev).abs() < 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rank_actions_two_columns() {
+        let (advisor, mut triple_state, _) = mid_game_advisor(0);
+        let chance_only = all_filled_except(&advisor.rule_set, &[ScoreCategory::Chance]);
+        triple_state.columns[2] = chance_only;
+        let mut memo = MapMemo::default();
+        solve_ev(&triple_state.columns[0], &mut memo, &advisor.rule_set);
+        memo.set(
+            chance_only,
+            scorecard_state_ev(&chance_only, &memo, &advisor.rule_set),
+        );
+        let mut advisor = TripleAdvisor::new(memo, advisor.rule_set);
+        let dice_state = dice_state([0, 1, 0, 0, 1, 3], 1);
+        let ranked_actions = advisor.rank_actions(&triple_state, &dice_state);
+        let advice = advisor.advise(&triple_state, &dice_state).unwrap();
+        assert_eq!(ranked_actions[0].action, advice.action);
+        assert!((ranked_actions[0].ev - advice.ev).abs() < 1e-9);
+        assert_eq!(ranked_actions[0].loss, 0.0);
+        for pair in ranked_actions.windows(2) {
+            assert!(pair[0].ev >= pair[1].ev);
+        }
+        // every open box of either column can be scored.
+        let num_scores = ranked_actions
+            .iter()
+            .filter(|x| matches!(x.action, TripleAction::Score(..)))
+            .count();
+        assert_eq!(num_scores, 4 + 1);
+        assert!(
+            advisor
+                .rank_actions(&chance_only_advisor(&[]).1, &dice_state)
+                .is_empty()
+        );
+    }
+}
@@ -1,10 +1,71 @@
-use enum_map::{Enum, EnumMap, enum_map};
+use enum_map::{Enum, EnumMap};
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// How a yahtzee may be scored once the matching upper section box is filled. The joker rules
+/// only constrain Forced and Original once the yahtzee box is filled, as before that a yahtzee can
+/// be scored in any open box, without joker scoring.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum JokerRule {
+    /// The official rule. The matching upper section box must be used if it is open, then any
+    /// open lower section box (with joker scoring), and only then an open upper section box for 0.
     Forced,
+    /// Any open box may be used, with joker scoring whenever the matching upper section box is
+    /// filled.
     FreeChoice,
+    /// The matching upper section box must be used if it is open, and otherwise any open box may
+    /// be used, with joker scoring.
+    Original,
+    /// A yahtzee is scored like any other roll, as in Scandinavian Yatzy.
+    None,
+}
+
+/// The family of dice game being played, which decides the score categories and how each of them
+/// is scored.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Variant {
+    Yahtzee,
+    /// Scandinavian Yatzy, which adds One Pair and Two Pairs, and scores three and four of a kind
+    /// and the full house by the dice that make them up.
+    Yatzy,
+    /// The original Yacht, which has no three of a kind, scores four of a kind and the full house
+    /// by the dice that make them up, and has a fixed-value Little (1-5) and Big (2-6) Straight in
+    /// place of the small and large straights.
+    Yacht,
+}
+
+/// The dice rolled each turn. Every variant scores the faces 1 to 6, so a die has at most six
+/// faces.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Dice {
+    num_dice: u8,
+    num_faces: u8,
+}
+
+/// The rules of a game, for house rules which differ from the standard game.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct RuleSet {
+    pub variant: Variant,
+    pub dice: Dice,
+    pub joker_rule: JokerRule,
+    /// Only used by variants whose full house has a fixed value.
+    pub full_house_value: u8,
+    pub small_straight_value: u8,
+    pub large_straight_value: u8,
+    pub yahtzee_value: u8,
+    /// Awarded for each yahtzee after the first, if the yahtzee box was scored rather than
+    /// scratched.
+    pub yahtzee_bonus_value: u8,
+    pub upper_section_bonus_value: u8,
+    /// The upper section score at which the bonus is awarded. Must be at most
+    /// CappedUpperSectionScore::MAX_CAP.
+    pub upper_section_bonus_threshold: u8,
+    /// The rerolls of each turn, after the first roll.
+    pub rerolls: u8,
+    /// Under the Maxi Yatzy rule, rerolls left unused when scoring are saved for later turns, up
+    /// to this many. 0 if rerolls are never saved. rerolls + max_saved_rolls must be at most
+    /// RollsLeft::MAX.
+    pub max_saved_rolls: u8,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -13,23 +74,40 @@ pub struct DiceState {
     pub rolls_left: RollsLeft,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Hash)]
+/// The state of a scorecard as far as the solver is concerned. Categories outside the variant
+/// being played are filled from the start, so the default is the empty Yahtzee scorecard. See
+/// `ScorecardState::new_game` for other variants.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct ScorecardState {
     pub capped_upper_section_score: CappedUpperSectionScore,
+    variant: Variant,
     score_category_state: EnumMap<ScoreCategory, ScoreCategoryState>,
 }
 
+/// How many dice show each face. Faces beyond the dice's number of faces are always 0.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-pub struct RollCounts([u8; RollCounts::NUM_FACES]);
+pub struct RollCounts {
+    counts: [u8; RollCounts::MAX_FACES],
+    dice: Dice,
+}
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct RollsLeft(u8);
 
+/// How many dice showing each face are kept, out of the dice's number of dice.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-pub struct KeepCounts([u8; RollCounts::NUM_FACES]);
+pub struct KeepCounts {
+    counts: [u8; RollCounts::MAX_FACES],
+    dice: Dice,
+}
 
+/// The upper section score, capped at the bonus threshold of the game, as past it the score makes
+/// no difference.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-pub struct CappedUpperSectionScore(u8);
+pub struct CappedUpperSectionScore {
+    score: u8,
+    cap: u8,
+}
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Enum, EnumIter)]
 pub enum ScoreCategory {
@@ -46,6 +124,16 @@ pub enum ScoreCategory {
     LargeStraight,
     Yahtzee,
     Chance,
+    OnePair,
+    TwoPairs,
+}
+
+/// A decision within a turn: either reroll every die not kept, or end the turn by scoring a
+/// category.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Action {
+    Keep(KeepCounts),
+    Score(ScoreCategory),
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Hash)]
@@ -58,10 +146,19 @@ pub enum ScoreCategoryState {
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum ConstructionError {
+    ValueTooSmall { min: u8, got: u8 },
     ValueTooLarge { max: u8, got: u8 },
     SumMismatch { expected: u8, got: u8 },
 }
 
+/// Why a RuleSet can't be played, see `RuleSet::validate`.
+#[derive(Debug, Eq, PartialEq)]
+pub enum RuleSetError {
+    PointsTooLarge { max: u16, got: u16 },
+    ThresholdTooLarge { max: u8, got: u8 },
+    TooManyRolls { max: u8, got: u16 },
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum ScoringError {
     InvalidScoreCategory { category: ScoreCategory },
@@ -73,13 +170,55 @@ pub enum SubtractionError {
 }
 
 impl ScorecardState {
+    /// * `variant` - The variant being played. Its categories take their states from
+    ///   `score_category_state`, and every other category is filled.
+    pub fn new(
+        variant: Variant,
+        capped_upper_section_score: CappedUpperSectionScore,
+        mut score_category_state: EnumMap<ScoreCategory, ScoreCategoryState>,
+    ) -> Self {
+        for score_category in ScoreCategory::iter().filter(|x| !variant.has_category(*x)) {
+            score_category_state[score_category] = ScoreCategoryState::Scored;
+        }
+        Self {
+            capped_upper_section_score,
+            variant,
+            score_category_state,
+        }
+    }
+
+    /// The empty scorecard of the variant, with every category outside it already filled.
+    pub fn new_game(rule_set: &RuleSet) -> Self {
+        rule_set.validate().expect("The rule set must be valid.");
+        let capped_upper_section_score =
+            CappedUpperSectionScore::new(0, rule_set.upper_section_cap())
+                .expect("validate checks the upper section bonus threshold.");
+        Self::new(
+            rule_set.variant,
+            capped_upper_section_score,
+            EnumMap::default(),
+        )
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
     pub fn score_category_state(&self) -> &EnumMap<ScoreCategory, ScoreCategoryState> {
         &self.score_category_state
     }
 
+    /// The number of score categories of the variant which have been scored or scratched.
+    pub fn num_filled(&self) -> usize {
+        ScoreCategory::iter()
+            .filter(|&x| self.variant.has_category(x))
+            .filter(|&x| self.score_category_state()[x] != ScoreCategoryState::Unscored)
+            .count()
+    }
+
     pub fn score(&self, category: ScoreCategory, score: u8) -> Result<Self, ScoringError> {
         if self.score_category_state()[category] != ScoreCategoryState::Unscored {
-            return Err(ScoringError::InvalidScoreCategory { category: category });
+            return Err(ScoringError::InvalidScoreCategory { category });
         }
         let mut new_score_category_state = *self.score_category_state();
         if category == ScoreCategory::Yahtzee && score == 0 {
@@ -93,11 +232,41 @@ impl ScorecardState {
         }
         Ok(Self {
             capped_upper_section_score: new_capped_upper_section_score,
+            variant: self.variant,
             score_category_state: new_score_category_state,
         })
     }
 }
 
+impl Default for ScorecardState {
+    fn default() -> Self {
+        Self::new_game(&RuleSet::default())
+    }
+}
+
+impl Variant {
+    /// The number of categories of this variant's scorecard.
+    pub fn num_categories(&self) -> usize {
+        ScoreCategory::iter()
+            .filter(|&x| self.has_category(x))
+            .count()
+    }
+
+    /// Whether the category is part of this variant's scorecard.
+    pub fn has_category(&self, category: ScoreCategory) -> bool {
+        match self {
+            Variant::Yahtzee => {
+                !matches!(category, ScoreCategory::OnePair | ScoreCategory::TwoPairs)
+            }
+            Variant::Yatzy => true,
+            Variant::Yacht => !matches!(
+                category,
+                ScoreCategory::ThreeOfAKind | ScoreCategory::OnePair | ScoreCategory::TwoPairs
+            ),
+        }
+    }
+}
+
 impl ScoreCategory {
     pub fn is_upper_section(&self) -> bool {
         matches!(
@@ -116,52 +285,115 @@ impl ScoreCategory {
     }
 }
 
+impl Dice {
+    pub const MAX_DICE: u8 = 8;
+    /// Five six-sided dice, as in Yahtzee and Yatzy.
+    pub const STANDARD: Self = Self {
+        num_dice: 5,
+        num_faces: 6,
+    };
+    /// Six six-sided dice, as in Maxi Yatzy.
+    pub const MAXI: Self = Self {
+        num_dice: 6,
+        num_faces: 6,
+    };
+
+    /// * `num_dice` - On [1..=MAX_DICE].
+    /// * `num_faces` - On [1..=RollCounts::MAX_FACES].
+    pub fn new(num_dice: u8, num_faces: u8) -> Result<Self, ConstructionError> {
+        for (value, max) in [
+            (num_dice, Self::MAX_DICE),
+            (num_faces, RollCounts::MAX_FACES as u8),
+        ] {
+            if value == 0 {
+                return Err(ConstructionError::ValueTooSmall { min: 1, got: value });
+            }
+            if value > max {
+                return Err(ConstructionError::ValueTooLarge { max, got: value });
+            }
+        }
+        Ok(Self {
+            num_dice,
+            num_faces,
+        })
+    }
+
+    pub fn num_dice(&self) -> u8 {
+        self.num_dice
+    }
+
+    pub fn num_faces(&self) -> u8 {
+        self.num_faces
+    }
+
+    /// Checks that `counts` only uses the faces of these dice, with exactly num_dice dice in total
+    /// if `exact`, and at most num_dice otherwise.
+    fn check_counts(
+        &self,
+        counts: &[u8; RollCounts::MAX_FACES],
+        exact: bool,
+    ) -> Result<(), ConstructionError> {
+        let max = self.num_dice;
+        if let Some(&got) = counts.iter().find(|&&x| x > max) {
+            return Err(ConstructionError::ValueTooLarge { max, got });
+        }
+        if let Some(&got) = counts[self.num_faces as usize..].iter().find(|&&x| x > 0) {
+            return Err(ConstructionError::ValueTooLarge { max: 0, got });
+        }
+        let total = counts.iter().sum::<u8>();
+        if total > max || (exact && total != max) {
+            return Err(ConstructionError::SumMismatch {
+                expected: max,
+                got: total,
+            });
+        }
+        Ok(())
+    }
+}
+
 impl RollCounts {
-    pub const NUM_DICE: usize = 5;
-    pub const NUM_FACES: usize = 6;
+    /// Rolls are stored as counts of up to this many faces, whatever the dice.
+    pub const MAX_FACES: usize = 6;
+
+    /// * `counts` - How many dice show each face, which must add up to the number of dice.
+    /// * `dice` - The dice that were rolled.
+    pub fn new(counts: [u8; Self::MAX_FACES], dice: Dice) -> Result<Self, ConstructionError> {
+        dice.check_counts(&counts, true)?;
+        Ok(Self { counts, dice })
+    }
 
-    pub fn roll_counts(&self) -> &[u8; Self::NUM_FACES] {
-        &self.0
+    pub fn roll_counts(&self) -> &[u8; Self::MAX_FACES] {
+        &self.counts
+    }
+
+    pub fn dice(&self) -> &Dice {
+        &self.dice
     }
 
     pub fn subtract(&self, keep_counts: &KeepCounts) -> Result<KeepCounts, SubtractionError> {
         let mut result = *self.roll_counts();
-        for i in 0..RollCounts::NUM_DICE {
-            if result[i] < keep_counts.keep_counts()[i] {
+        for (i, (count, &keep)) in result.iter_mut().zip(keep_counts.keep_counts()).enumerate() {
+            if *count < keep {
                 return Err(SubtractionError::Underflow { index: i as u8 });
             }
-            result[i] -= keep_counts.keep_counts()[i];
+            *count -= keep;
         }
-        Ok(KeepCounts::try_from(result).unwrap())
+        Ok(KeepCounts::new(result, self.dice).unwrap())
     }
 }
 
-impl TryFrom<[u8; RollCounts::NUM_FACES]> for RollCounts {
+impl TryFrom<[u8; RollCounts::MAX_FACES]> for RollCounts {
     type Error = ConstructionError;
 
-    fn try_from(value: [u8; Self::NUM_FACES]) -> Result<Self, Self::Error> {
-        if value.iter().any(|&x| x > Self::NUM_DICE as u8) {
-            Err(Self::Error::ValueTooLarge {
-                max: Self::NUM_DICE as u8,
-                got: *value
-                    .iter()
-                    .filter(|&&x| x > Self::NUM_DICE as u8)
-                    .next()
-                    .unwrap(),
-            })
-        } else if value.iter().sum::<u8>() != Self::NUM_DICE as u8 {
-            Err(Self::Error::SumMismatch {
-                expected: Self::NUM_DICE as u8,
-                got: value.iter().sum::<u8>(),
-            })
-        } else {
-            Ok(Self(value))
-        }
+    /// A roll of the standard five six-sided dice.
+    fn try_from(value: [u8; Self::MAX_FACES]) -> Result<Self, Self::Error> {
+        Self::new(value, Dice::STANDARD)
     }
 }
 
 impl RollsLeft {
-    pub const MAX: u8 = 2;
+    /// The most rolls left of any turn, including saved rolls.
+    pub const MAX: u8 = 8;
 
     pub fn rolls_left(&self) -> &u8 {
         &self.0
@@ -172,8 +404,11 @@ impl TryFrom<u8> for RollsLeft {
     type Error = ConstructionError;
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
-        if value > 2 {
-            Err(Self::Error::ValueTooLarge { max: 2, got: value })
+        if value > Self::MAX {
+            Err(Self::Error::ValueTooLarge {
+                max: Self::MAX,
+                got: value,
+            })
         } else {
             Ok(Self(value))
         }
@@ -181,65 +416,190 @@ impl TryFrom<u8> for RollsLeft {
 }
 
 impl KeepCounts {
-    pub fn keep_counts(&self) -> &[u8; RollCounts::NUM_FACES] {
-        &self.0
+    /// * `counts` - How many dice showing each face are kept, at most the number of dice in total.
+    /// * `dice` - The dice that were rolled.
+    pub fn new(counts: [u8; RollCounts::MAX_FACES], dice: Dice) -> Result<Self, ConstructionError> {
+        dice.check_counts(&counts, false)?;
+        Ok(Self { counts, dice })
+    }
+
+    /// Keeping none of the dice, i.e. rolling all of them.
+    pub fn none(dice: Dice) -> Self {
+        Self {
+            counts: [0; RollCounts::MAX_FACES],
+            dice,
+        }
+    }
+
+    pub fn keep_counts(&self) -> &[u8; RollCounts::MAX_FACES] {
+        &self.counts
+    }
+
+    pub fn dice(&self) -> &Dice {
+        &self.dice
     }
 }
 
-impl TryFrom<[u8; RollCounts::NUM_FACES]> for KeepCounts {
+impl TryFrom<[u8; RollCounts::MAX_FACES]> for KeepCounts {
     type Error = ConstructionError;
 
-    fn try_from(value: [u8; RollCounts::NUM_FACES]) -> Result<Self, Self::Error> {
-        if value.iter().any(|&x| x > RollCounts::NUM_DICE as u8) {
-            Err(Self::Error::ValueTooLarge {
-                max: RollCounts::NUM_DICE as u8,
-                got: *value
-                    .iter()
-                    .filter(|&&x| x > RollCounts::NUM_DICE as u8)
-                    .next()
-                    .unwrap(),
-            })
-        } else if value.iter().sum::<u8>() > RollCounts::NUM_DICE as u8 {
-            Err(Self::Error::SumMismatch {
-                expected: RollCounts::NUM_DICE as u8,
-                got: value.iter().sum::<u8>(),
-            })
-        } else {
-            Ok(Self(value))
-        }
+    /// A keep of the standard five six-sided dice.
+    fn try_from(value: [u8; RollCounts::MAX_FACES]) -> Result<Self, Self::Error> {
+        Self::new(value, Dice::STANDARD)
     }
 }
 
-impl CappedUpperSectionScore {
-    pub const CAP: u8 = 63;
+impl RuleSet {
+    /// The standard game, with the given joker rule.
+    pub const fn standard(joker_rule: JokerRule) -> Self {
+        Self {
+            variant: Variant::Yahtzee,
+            dice: Dice::STANDARD,
+            joker_rule,
+            full_house_value: 25,
+            small_straight_value: 30,
+            large_straight_value: 40,
+            yahtzee_value: 50,
+            yahtzee_bonus_value: 100,
+            upper_section_bonus_value: 35,
+            upper_section_bonus_threshold: CappedUpperSectionScore::STANDARD_CAP,
+            rerolls: 2,
+            max_saved_rolls: 0,
+        }
+    }
 
-    pub fn score(&self) -> u8 {
-        self.0
+    /// Scandinavian Yatzy: straights worth their sums of 15 and 20, a 50 point upper section
+    /// bonus, and no joker or bonus yatzies.
+    pub const fn yatzy() -> Self {
+        Self {
+            variant: Variant::Yatzy,
+            dice: Dice::STANDARD,
+            joker_rule: JokerRule::None,
+            full_house_value: 0,
+            small_straight_value: 15,
+            large_straight_value: 20,
+            yahtzee_value: 50,
+            yahtzee_bonus_value: 0,
+            upper_section_bonus_value: 50,
+            upper_section_bonus_threshold: CappedUpperSectionScore::STANDARD_CAP,
+            rerolls: 2,
+            max_saved_rolls: 0,
+        }
     }
 
-    pub fn add_clamped(&self, other: u8) -> Self {
-        let clamped_other = std::cmp::min(other, Self::CAP);
-        Self(std::cmp::min(self.0 + clamped_other, Self::CAP))
+    /// The original Yacht: both straights worth 30, and no upper section bonus, joker or bonus
+    /// yachts.
+    pub const fn yacht() -> Self {
+        Self {
+            variant: Variant::Yacht,
+            dice: Dice::STANDARD,
+            joker_rule: JokerRule::None,
+            full_house_value: 0,
+            small_straight_value: 30,
+            large_straight_value: 30,
+            yahtzee_value: 50,
+            yahtzee_bonus_value: 0,
+            upper_section_bonus_value: 0,
+            upper_section_bonus_threshold: CappedUpperSectionScore::STANDARD_CAP,
+            rerolls: 2,
+            max_saved_rolls: 0,
+        }
+    }
+
+    /// The cap of the upper section score of the game's scorecard states. The upper section score
+    /// only matters up to the bonus threshold, and not at all without a bonus.
+    pub fn upper_section_cap(&self) -> u8 {
+        if self.upper_section_bonus_value == 0 {
+            0
+        } else {
+            self.upper_section_bonus_threshold
+        }
+    }
+
+    /// Checks that the rules can be played: the points of any one scoring, including both
+    /// bonuses, must fit in a u8, the bonus threshold must be at most
+    /// CappedUpperSectionScore::MAX_CAP, and a turn can have at most RollsLeft::MAX rolls left.
+    pub fn validate(&self) -> Result<(), RuleSetError> {
+        // no category scores more than every die showing a six, or one of the fixed values.
+        let max_category_score = [
+            self.dice.num_dice() * 6,
+            self.full_house_value,
+            self.small_straight_value,
+            self.large_straight_value,
+            self.yahtzee_value,
+        ]
+        .into_iter()
+        .max()
+        .unwrap() as u16;
+        let max_points = max_category_score
+            + self.yahtzee_bonus_value as u16
+            + self.upper_section_bonus_value as u16;
+        if max_points > u8::MAX as u16 {
+            return Err(RuleSetError::PointsTooLarge {
+                max: u8::MAX as u16,
+                got: max_points,
+            });
+        }
+        if self.upper_section_bonus_threshold > CappedUpperSectionScore::MAX_CAP {
+            return Err(RuleSetError::ThresholdTooLarge {
+                max: CappedUpperSectionScore::MAX_CAP,
+                got: self.upper_section_bonus_threshold,
+            });
+        }
+        let max_rolls_left = self.rerolls as u16 + self.max_saved_rolls as u16;
+        if max_rolls_left > RollsLeft::MAX as u16 {
+            return Err(RuleSetError::TooManyRolls {
+                max: RollsLeft::MAX,
+                got: max_rolls_left,
+            });
+        }
+        Ok(())
     }
 }
 
-impl Default for CappedUpperSectionScore {
+impl Default for RuleSet {
+    /// The standard game, with the official (forced) joker rule.
     fn default() -> Self {
-        Self(0)
+        Self::standard(JokerRule::Forced)
     }
 }
 
-impl TryFrom<u8> for CappedUpperSectionScore {
-    type Error = ConstructionError;
+impl CappedUpperSectionScore {
+    /// The cap of the standard game, whose bonus is awarded at 63.
+    pub const STANDARD_CAP: u8 = 63;
+    /// The highest cap supported, which is the bonus threshold of 6-dice Maxi Yatzy.
+    pub const MAX_CAP: u8 = 84;
+
+    /// * `score` - On [0..=cap].
+    /// * `cap` - On [0..=MAX_CAP].
+    pub fn new(score: u8, cap: u8) -> Result<Self, ConstructionError> {
+        if cap > Self::MAX_CAP {
+            return Err(ConstructionError::ValueTooLarge {
+                max: Self::MAX_CAP,
+                got: cap,
+            });
+        }
+        if score > cap {
+            return Err(ConstructionError::ValueTooLarge {
+                max: cap,
+                got: score,
+            });
+        }
+        Ok(Self { score, cap })
+    }
 
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        if value > Self::CAP {
-            Err(Self::Error::ValueTooLarge {
-                max: Self::CAP,
-                got: value,
-            })
-        } else {
-            Ok(Self(value))
+    pub fn score(&self) -> u8 {
+        self.score
+    }
+
+    pub fn cap(&self) -> u8 {
+        self.cap
+    }
+
+    pub fn add_clamped(&self, other: u8) -> Self {
+        Self {
+            score: std::cmp::min(self.score.saturating_add(other), self.cap),
+            cap: self.cap,
         }
     }
 }
@@ -260,7 +620,7 @@ mod tests {
         assert_eq!(
             result,
             Err(ConstructionError::SumMismatch {
-                expected: RollCounts::NUM_DICE as u8,
+                expected: Dice::STANDARD.num_dice(),
                 got: 6
             })
         );
@@ -273,7 +633,7 @@ mod tests {
         assert_eq!(
             result,
             Err(ConstructionError::ValueTooLarge {
-                max: RollCounts::NUM_DICE as u8,
+                max: Dice::STANDARD.num_dice(),
                 got: 254
             })
         );
@@ -283,14 +643,19 @@ mod tests {
     fn rolls_left_valid_initialisation() {
         let result = RollsLeft::try_from(2);
         assert!(result.is_ok());
+        let result = RollsLeft::try_from(RollsLeft::MAX);
+        assert!(result.is_ok());
     }
 
     #[test]
     fn rolls_left_invalid_initialisation() {
-        let result = RollsLeft::try_from(3);
+        let result = RollsLeft::try_from(RollsLeft::MAX + 1);
         assert_eq!(
             result,
-            Err(ConstructionError::ValueTooLarge { max: 2, got: 3 })
+            Err(ConstructionError::ValueTooLarge {
+                max: RollsLeft::MAX,
+                got: RollsLeft::MAX + 1
+            })
         );
     }
 
@@ -306,7 +671,7 @@ mod tests {
         assert_eq!(
             result,
             Err(ConstructionError::SumMismatch {
-                expected: RollCounts::NUM_DICE as u8,
+                expected: Dice::STANDARD.num_dice(),
                 got: 6
             })
         );
@@ -319,7 +684,7 @@ mod tests {
         assert_eq!(
             result,
             Err(ConstructionError::ValueTooLarge {
-                max: RollCounts::NUM_DICE as u8,
+                max: Dice::STANDARD.num_dice(),
                 got: 254
             })
         );
@@ -327,9 +692,86 @@ mod tests {
 
     #[test]
     fn capped_upper_section_score_clamped_addition() {
-        let capped_score = CappedUpperSectionScore::try_from(CappedUpperSectionScore::CAP).unwrap();
+        let capped_score = CappedUpperSectionScore::new(
+            CappedUpperSectionScore::STANDARD_CAP,
+            CappedUpperSectionScore::STANDARD_CAP,
+        )
+        .unwrap();
         let result = capped_score.add_clamped(u8::MAX);
-        assert_eq!(result.score(), CappedUpperSectionScore::CAP);
+        assert_eq!(result.score(), CappedUpperSectionScore::STANDARD_CAP);
+        let capped_score = CappedUpperSectionScore::new(80, 84).unwrap();
+        assert_eq!(capped_score.add_clamped(3).score(), 83);
+        assert_eq!(capped_score.add_clamped(30).score(), 84);
+    }
+
+    #[test]
+    fn capped_upper_section_score_initialisation() {
+        assert_eq!(
+            CappedUpperSectionScore::new(64, 63),
+            Err(ConstructionError::ValueTooLarge { max: 63, got: 64 })
+        );
+        assert_eq!(
+            CappedUpperSectionScore::new(0, CappedUpperSectionScore::MAX_CAP + 1),
+            Err(ConstructionError::ValueTooLarge {
+                max: CappedUpperSectionScore::MAX_CAP,
+                got: CappedUpperSectionScore::MAX_CAP + 1
+            })
+        );
+        // the cap follows the bonus threshold, and is 0 without a bonus.
+        let maxi = RuleSet {
+            dice: Dice::MAXI,
+            upper_section_bonus_threshold: 84,
+            ..RuleSet::yatzy()
+        };
+        let scorecard_state = ScorecardState::new_game(&maxi);
+        assert_eq!(scorecard_state.capped_upper_section_score.cap(), 84);
+        let scorecard_state = ScorecardState::new_game(&RuleSet::yacht());
+        assert_eq!(scorecard_state.capped_upper_section_score.cap(), 0);
+        let scorecard_state = scorecard_state.score(ScoreCategory::Sixes, 30).unwrap();
+        assert_eq!(scorecard_state.capped_upper_section_score.score(), 0);
+    }
+
+    #[test]
+    fn rule_set_validation() {
+        for rule_set in [
+            RuleSet::standard(JokerRule::Forced),
+            RuleSet::yatzy(),
+            RuleSet::yacht(),
+        ] {
+            assert_eq!(rule_set.validate(), Ok(()));
+        }
+        // a joker yahtzee in the upper section can take both bonuses at once.
+        let rule_set = RuleSet {
+            yahtzee_bonus_value: 200,
+            ..RuleSet::standard(JokerRule::Forced)
+        };
+        assert_eq!(
+            rule_set.validate(),
+            Err(RuleSetError::PointsTooLarge {
+                max: 255,
+                got: 50 + 200 + 35
+            })
+        );
+        let rule_set = RuleSet {
+            upper_section_bonus_threshold: CappedUpperSectionScore::MAX_CAP + 1,
+            ..RuleSet::yatzy()
+        };
+        assert_eq!(
+            rule_set.validate(),
+            Err(RuleSetError::ThresholdTooLarge {
+                max: CappedUpperSectionScore::MAX_CAP,
+                got: CappedUpperSectionScore::MAX_CAP + 1
+            })
+        );
+        let rule_set = RuleSet {
+            rerolls: 4,
+            max_saved_rolls: 5,
+            ..RuleSet::yatzy()
+        };
+        assert_eq!(
+            rule_set.validate(),
+            Err(RuleSetError::TooManyRolls { max: 8, got: 9 })
+        );
     }
 
     #[test]
@@ -338,13 +780,15 @@ mod tests {
             .score(ScoreCategory::Yahtzee, 50)
             .unwrap();
         let expected_capped_upper_section_score = 0;
-        let mut expected_score_category_state =
-            EnumMap::<ScoreCategory, ScoreCategoryState>::default();
+        let mut expected_score_category_state = *ScorecardState::default().score_category_state();
         expected_score_category_state[ScoreCategory::Yahtzee] = ScoreCategoryState::Scored;
         let expected_state = ScorecardState {
-            capped_upper_section_score: CappedUpperSectionScore(
+            variant: Variant::Yahtzee,
+            capped_upper_section_score: CappedUpperSectionScore::new(
                 expected_capped_upper_section_score,
-            ),
+                CappedUpperSectionScore::STANDARD_CAP,
+            )
+            .unwrap(),
             score_category_state: expected_score_category_state,
         };
         assert_eq!(scorecard_state, expected_state);
@@ -356,13 +800,15 @@ mod tests {
             .score(ScoreCategory::Yahtzee, 0)
             .unwrap();
         let expected_capped_upper_section_score = 0;
-        let mut expected_score_category_state =
-            EnumMap::<ScoreCategory, ScoreCategoryState>::default();
+        let mut expected_score_category_state = *ScorecardState::default().score_category_state();
         expected_score_category_state[ScoreCategory::Yahtzee] = ScoreCategoryState::Scratched;
         let expected_state = ScorecardState {
-            capped_upper_section_score: CappedUpperSectionScore(
+            variant: Variant::Yahtzee,
+            capped_upper_section_score: CappedUpperSectionScore::new(
                 expected_capped_upper_section_score,
-            ),
+                CappedUpperSectionScore::STANDARD_CAP,
+            )
+            .unwrap(),
             score_category_state: expected_score_category_state,
         };
         assert_eq!(scorecard_state, expected_state);
@@ -374,13 +820,15 @@ mod tests {
             .score(ScoreCategory::Aces, 3)
             .unwrap();
         let expected_capped_upper_section_score = 3;
-        let mut expected_score_category_state =
-            EnumMap::<ScoreCategory, ScoreCategoryState>::default();
+        let mut expected_score_category_state = *ScorecardState::default().score_category_state();
         expected_score_category_state[ScoreCategory::Aces] = ScoreCategoryState::Scored;
         let expected_state = ScorecardState {
-            capped_upper_section_score: CappedUpperSectionScore(
+            variant: Variant::Yahtzee,
+            capped_upper_section_score: CappedUpperSectionScore::new(
                 expected_capped_upper_section_score,
-            ),
+                CappedUpperSectionScore::STANDARD_CAP,
+            )
+            .unwrap(),
             score_category_state: expected_score_category_state,
         };
         assert_eq!(scorecard_state, expected_state);
@@ -401,20 +849,70 @@ mod tests {
     }
 
     #[test]
-    fn num_faces_as_expected() {
+    fn subtract_includes_last_face() {
+        let roll_counts = RollCounts::try_from([0, 0, 0, 0, 2, 3]).unwrap();
+        let keep_counts = KeepCounts::try_from([0, 0, 0, 0, 0, 4]).unwrap();
+        assert_eq!(
+            roll_counts.subtract(&keep_counts),
+            Err(SubtractionError::Underflow { index: 5 })
+        );
+    }
+
+    #[test]
+    fn num_filled() {
+        let scorecard_state = ScorecardState::default()
+            .score(ScoreCategory::Aces, 3)
+            .unwrap()
+            .score(ScoreCategory::Yahtzee, 0)
+            .unwrap();
+        assert_eq!(scorecard_state.num_filled(), 2);
+        assert_eq!(
+            ScorecardState::new_game(&RuleSet::yatzy())
+                .score(ScoreCategory::OnePair, 8)
+                .unwrap()
+                .num_filled(),
+            1
+        );
+    }
+
+    #[test]
+    fn dice_initialisation() {
+        assert_eq!(Dice::new(5, 6), Ok(Dice::STANDARD));
+        assert_eq!(Dice::new(6, 6), Ok(Dice::MAXI));
+        assert_eq!(
+            Dice::new(0, 6),
+            Err(ConstructionError::ValueTooSmall { min: 1, got: 0 })
+        );
+        assert_eq!(
+            Dice::new(5, 7),
+            Err(ConstructionError::ValueTooLarge { max: 6, got: 7 })
+        );
         assert_eq!(
-            RollCounts::NUM_FACES,
-            6,
-            "This constant was only named to reduce magic numbers in the code. It is absolutely not intended to be changed, and things will break if it is changed."
+            Dice::new(9, 6),
+            Err(ConstructionError::ValueTooLarge { max: 8, got: 9 })
         );
     }
 
     #[test]
-    fn num_dice_as_expected() {
+    fn roll_counts_of_other_dice() {
+        assert!(RollCounts::new([1, 1, 1, 1, 1, 1], Dice::MAXI).is_ok());
         assert_eq!(
-            RollCounts::NUM_DICE as u8,
-            5,
-            "This constant was only named to reduce magic numbers in the code. It is absolutely not intended to be changed, and things will break if it is changed."
+            RollCounts::new([1, 1, 1, 1, 1, 0], Dice::MAXI),
+            Err(ConstructionError::SumMismatch {
+                expected: 6,
+                got: 5
+            })
+        );
+        let four_sided = Dice::new(5, 4).unwrap();
+        assert!(RollCounts::new([2, 1, 1, 1, 0, 0], four_sided).is_ok());
+        assert_eq!(
+            RollCounts::new([2, 1, 1, 0, 1, 0], four_sided),
+            Err(ConstructionError::ValueTooLarge { max: 0, got: 1 })
+        );
+        // the same counts of different dice are different rolls.
+        assert_ne!(
+            KeepCounts::new([1, 0, 0, 0, 0, 0], Dice::MAXI),
+            KeepCounts::try_from([1, 0, 0, 0, 0, 0])
         );
     }
 }
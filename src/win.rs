@@ -0,0 +1,245 @@
+use crate::advisor::{Advice, TurnSolution};
+use crate::distribution::ScoreDistribution;
+use crate::types::{Action, DiceState, RuleSet, ScorecardState};
+use crate::{MapMemo, Memo, VecMemo, dice_dp_with_value, downstream_transitions, first_roll_value};
+
+/// Our scorecard state along with the points we have scored so far, including bonuses.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct WinState {
+    pub scorecard_state: ScorecardState,
+    pub points: u16,
+}
+
+/// Maximises P(win) in a heads-up game, where we win if our final score is strictly greater than
+/// the opponent's. The opponent's final score is known up front, either exactly or as a
+/// distribution independent of how we play, e.g. because they have already finished.
+///
+/// WinStates carry the points scored so far, so like TargetStates they are solved on demand.
+pub struct WinSolver {
+    rule_set: RuleSet,
+    /// p_below[x] is the probability that the opponent scores less than x.
+    p_below: Vec<f64>,
+    memo: MapMemo<WinState, f64>,
+    turn_memo: MapMemo<WinState, TurnSolution>,
+}
+
+impl WinSolver {
+    /// * `rule_set` - The rules to score rolls with.
+    /// * `opponent` - The distribution of the opponent's final score.
+    pub fn new(rule_set: RuleSet, opponent: &ScoreDistribution) -> Self {
+        let mut p_below = vec![0f64];
+        for &p in opponent.probabilities() {
+            p_below.push(p_below.last().unwrap() + p);
+        }
+        Self {
+            rule_set,
+            p_below,
+            memo: MapMemo::default(),
+            turn_memo: MapMemo::default(),
+        }
+    }
+
+    /// * `rule_set` - The rules to score rolls with.
+    /// * `opponent_score` - The opponent's final score.
+    pub fn against_score(rule_set: RuleSet, opponent_score: u16) -> Self {
+        Self::new(
+            rule_set,
+            &ScoreDistribution::point_mass(opponent_score as usize),
+        )
+    }
+
+    /// The probability of winning before the first roll of a turn, when playing to maximise it.
+    pub fn probability(&mut self, win_state: &WinState) -> f64 {
+        let win_state = self.clamp(win_state);
+        if win_state.scorecard_state.is_terminal() || self.is_won(&win_state) {
+            return self.p_below[win_state.points as usize];
+        }
+        if let Some(&p) = self.memo.get(&win_state) {
+            return p;
+        }
+        self.solve_downstream(&win_state);
+        let (dice_value_memo, _) = self.dice_dp(&win_state);
+        let p = first_roll_value(&dice_value_memo, &self.rule_set.dice, self.rule_set.rerolls);
+        self.memo.set(win_state, p);
+        p
+    }
+
+    /// The action which maximises the probability of winning from the given decision point, or
+    /// None if the game is over. The `ev` of the advice is that probability.
+    ///
+    /// * `win_state` - Our current scorecard and points.
+    /// * `dice_state` - The current dice and the number of rerolls left this turn.
+    pub fn advise(&mut self, win_state: &WinState, dice_state: &DiceState) -> Option<Advice> {
+        let win_state = self.clamp(win_state);
+        if win_state.scorecard_state.is_terminal() {
+            return None;
+        }
+        if self.turn_memo.get(&win_state).is_none() {
+            self.solve_downstream(&win_state);
+            let (ev_memo, policy_memo) = self.dice_dp(&win_state);
+            self.turn_memo.set(
+                win_state,
+                TurnSolution {
+                    ev_memo,
+                    policy_memo,
+                },
+            );
+        }
+        let turn_solution = self.turn_memo.get(&win_state).unwrap();
+        Some(Advice {
+            action: *turn_solution
+                .policy_memo
+                .get(dice_state)
+                .expect("dice_dp has an action for every dice state of a non-terminal state."),
+            ev: *turn_solution
+                .ev_memo
+                .get(dice_state)
+                .expect("dice_dp fills in every dice state."),
+        })
+    }
+
+    /// Beyond the opponent's highest possible score every number of points is as good as any
+    /// other, so we share one WinState between them.
+    fn clamp(&self, win_state: &WinState) -> WinState {
+        WinState {
+            scorecard_state: win_state.scorecard_state,
+            points: (win_state.points as usize).min(self.p_below.len() - 1) as u16,
+        }
+    }
+
+    /// Whether we have beaten every score the opponent could have.
+    fn is_won(&self, win_state: &WinState) -> bool {
+        win_state.points as usize == self.p_below.len() - 1
+    }
+
+    /// The dice DP of the win objective. Requires every downstream WinState to be solved.
+    fn dice_dp(
+        &self,
+        win_state: &WinState,
+    ) -> (VecMemo<DiceState, f64>, VecMemo<DiceState, Action>) {
+        dice_dp_with_value(
+            &win_state.scorecard_state,
+            &self.rule_set,
+            |target_scorecard_state, points| {
+                let target_win_state = self.clamp(&WinState {
+                    scorecard_state: *target_scorecard_state,
+                    points: win_state
+                        .points
+                        .checked_add(points as u16)
+                        .expect("The points of a game fit in a u16."),
+                });
+                if target_scorecard_state.is_terminal() || self.is_won(&target_win_state) {
+                    self.p_below[target_win_state.points as usize]
+                } else {
+                    *self
+                        .memo
+                        .get(&target_win_state)
+                        .expect("Every downstream win state must be solved first.")
+                }
+            },
+        )
+    }
+
+    /// Solves every WinState that can follow a scoring from this one.
+    fn solve_downstream(&mut self, win_state: &WinState) {
+        let rule_set = self.rule_set;
+        for (target_scorecard_state, points) in
+            downstream_transitions(&win_state.scorecard_state, &rule_set)
+        {
+            self.probability(&WinState {
+                scorecard_state: target_scorecard_state,
+                points: win_state
+                    .points
+                    .checked_add(points as u16)
+                    .expect("The points of a game fit in a u16."),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::all_filled_except;
+    use crate::target::{TargetSolver, TargetState};
+    use crate::types::JokerRule;
+    use crate::types::{RollCounts, RollsLeft, ScoreCategory};
+
+    fn chance_only(rule_set: &RuleSet, points: u16) -> WinState {
+        WinState {
+            scorecard_state: all_filled_except(rule_set, &[ScoreCategory::Chance]),
+            points,
+        }
+    }
+
+    #[test]
+    fn known_score_matches_target() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let mut target_solver = TargetSolver::new(rule_set);
+        for (points, opponent_score) in [(100, 120), (100, 125), (100, 80), (100, 130)] {
+            let win_state = chance_only(&rule_set, points);
+            let p = WinSolver::against_score(rule_set, opponent_score).probability(&win_state);
+            let expected = target_solver.probability(&TargetState {
+                scorecard_state: win_state.scorecard_state,
+                points_needed: (opponent_score + 1).saturating_sub(points),
+            });
+            assert!(
+                (p - expected).abs() < 1e-12,
+                "Got {p}, expected {expected} against {opponent_score}."
+            );
+        }
+    }
+
+    #[test]
+    fn opponent_distribution() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let mut probabilities = vec![0f64; 126];
+        probabilities[120] = 0.5;
+        probabilities[125] = 0.5;
+        let opponent = ScoreDistribution::new(probabilities);
+        let win_state = chance_only(&rule_set, 100);
+        let p = WinSolver::new(rule_set, &opponent).probability(&win_state);
+        // the best we can do against each score alone bounds how well we can do against both.
+        let p_120 = WinSolver::against_score(rule_set, 120).probability(&win_state);
+        let p_125 = WinSolver::against_score(rule_set, 125).probability(&win_state);
+        assert!(p <= 0.5 * p_120 + 0.5 * p_125 + 1e-12);
+        // playing to beat 125 also beats 120.
+        assert!(p >= p_125 - 1e-12);
+        assert!(p > p_125);
+    }
+
+    #[test]
+    fn already_won_and_lost() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let mut solver = WinSolver::against_score(rule_set, 120);
+        assert_eq!(solver.probability(&chance_only(&rule_set, 121)), 1.0);
+        assert_eq!(solver.probability(&chance_only(&rule_set, 200)), 1.0);
+        assert_eq!(solver.probability(&chance_only(&rule_set, 80)), 0.0);
+        let lost = WinState {
+            scorecard_state: chance_only(&rule_set, 0)
+                .scorecard_state
+                .score(ScoreCategory::Chance, 20)
+                .unwrap(),
+            points: 120,
+        };
+        assert_eq!(solver.probability(&lost), 0.0);
+        let dice_state = DiceState {
+            roll_counts: RollCounts::try_from([1, 1, 1, 1, 1, 0]).unwrap(),
+            rolls_left: RollsLeft::try_from(0).unwrap(),
+        };
+        assert_eq!(solver.advise(&lost, &dice_state), None);
+    }
+    #[test]
+    fn unbeatable_opponent() {
+        let rule_set = RuleSet::standard(JokerRule::FreeChoice);
+        let mut probabilities = vec![0f64; u16::MAX as usize + 1];
+        probabilities[120] = 0.5;
+        probabilities[u16::MAX as usize] = 0.5;
+        let opponent = ScoreDistribution::new(probabilities);
+        let mut solver = WinSolver::new(rule_set, &opponent);
+        // 120 is beaten by any roll, and the rest of the opponent's scores by none.
+        let p = solver.probability(&chance_only(&rule_set, 121));
+        assert!((p - 0.5).abs() < 1e-12, "Got {p}.");
+        assert_eq!(solver.probability(&chance_only(&rule_set, 80)), 0.0);
+    }
+}
@@ -1,33 +1,75 @@
 use crate::types::{
-    CappedUpperSectionScore, JokerRule, RollCounts, ScoreCategory, ScoreCategoryState,
-    ScorecardState, ScoringError,
+    Dice, JokerRule, RollCounts, RuleSet, ScoreCategory, ScoreCategoryState, ScorecardState,
+    ScoringError, Variant,
 };
-use enum_map::EnumMap;
+use std::sync::LazyLock;
 use strum::IntoEnumIterator;
 
-const YAHTZEE_BONUS_VALUE: u8 = 100;
-const UPPER_SECTION_BONUS_VALUE: u8 = 35;
+const UPPER_SECTION_CATEGORIES: usize = 6;
+
+/// The largest upper section score, with every die showing the face of its category.
+const MAX_UPPER_SECTION_SCORE: usize = Dice::MAX_DICE as usize * 21;
+
+/// For each number of dice, and each set of filled upper section categories (as a bitmask over
+/// Aces..=Sixes), which uncapped upper section scores can be reached. These don't depend on the
+/// cap, so one table serves every rule set.
+static UPPER_SECTION_REACHABILITY: LazyLock<
+    Vec<[[bool; MAX_UPPER_SECTION_SCORE + 1]; 1 << UPPER_SECTION_CATEGORIES]>,
+> = LazyLock::new(|| {
+    (0..=Dice::MAX_DICE)
+        .map(precompute_upper_section_reachability)
+        .collect()
+});
 
 impl ScorecardState {
-    /// All score categories that can be chosen for the given roll and joker rule.
+    /// All score categories that can be chosen for the given roll under the rules.
     pub fn valid_score_categories(
         &self,
         roll: &RollCounts,
-        joker_rule: JokerRule,
+        rule_set: &RuleSet,
     ) -> Vec<ScoreCategory> {
-        if let Some(yahtzee_category) = roll.is_yahtzee()
-            && joker_rule == JokerRule::Forced
-            && self.score_category_state()[yahtzee_category] == ScoreCategoryState::Unscored
-        {
-            return vec![yahtzee_category];
-        }
-        // aside from this special case, every roll is scorable in every category. It may just
-        // score 0 points.
         ScoreCategory::iter()
-            .filter(|&x| self.score_category_state()[x] == ScoreCategoryState::Unscored)
+            .filter(|&x| self.is_valid_score_category(roll, x, rule_set))
             .collect()
     }
 
+    /// Whether the roll can be scored in the category under the joker rule of the rules.
+    pub fn is_valid_score_category(
+        &self,
+        roll: &RollCounts,
+        category: ScoreCategory,
+        rule_set: &RuleSet,
+    ) -> bool {
+        let joker_rule = rule_set.joker_rule;
+        let is_open =
+            |x: ScoreCategory| self.score_category_state()[x] == ScoreCategoryState::Unscored;
+        if !is_open(category) || !rule_set.variant.has_category(category) {
+            return false;
+        }
+        // aside from yahtzees under the joker rules, every roll is scorable in every open
+        // category. It may just score 0 points.
+        let Some(yahtzee_category) = roll.is_yahtzee() else {
+            return true;
+        };
+        if matches!(joker_rule, JokerRule::FreeChoice | JokerRule::None)
+            || is_open(ScoreCategory::Yahtzee)
+        {
+            return true;
+        }
+        if is_open(yahtzee_category) {
+            return category == yahtzee_category;
+        }
+        match joker_rule {
+            JokerRule::Forced => {
+                !category.is_upper_section()
+                    || ScoreCategory::iter()
+                        .filter(|x| !x.is_upper_section())
+                        .all(|x| !is_open(x))
+            }
+            JokerRule::FreeChoice | JokerRule::Original | JokerRule::None => true,
+        }
+    }
+
     /// All valid score categories if the roll is not a yahtzee.
     pub fn valid_non_yahtzee_score_categories(&self) -> Vec<ScoreCategory> {
         ScoreCategory::iter()
@@ -42,32 +84,29 @@ impl ScorecardState {
         &self,
         roll: &RollCounts,
         category: ScoreCategory,
-        joker_rule: JokerRule,
+        rule_set: &RuleSet,
     ) -> Result<(u8, u8), ScoringError> {
         // test whether category is valid
-        if self.score_category_state()[category] != ScoreCategoryState::Unscored {
-            return Err(ScoringError::InvalidScoreCategory { category: category });
+        if !self.is_valid_score_category(roll, category, rule_set) {
+            return Err(ScoringError::InvalidScoreCategory { category });
         }
         let is_yahtzee = roll.is_yahtzee();
-        if let Some(yahtzee_category) = is_yahtzee
-            && joker_rule == JokerRule::Forced
-            && self.score_category_state()[yahtzee_category] == ScoreCategoryState::Unscored
-        {
-            return Err(ScoringError::InvalidScoreCategory { category: category });
-        }
-        let category_score = roll.score_value(category, self.is_joker(roll));
-        let yahtzee_bonus = if let Some(_) = is_yahtzee
+        let category_score =
+            roll.score_value(category, self.is_joker(roll, rule_set.joker_rule), rule_set);
+        let yahtzee_bonus = if is_yahtzee.is_some()
             && self.score_category_state()[ScoreCategory::Yahtzee] == ScoreCategoryState::Scored
         {
-            YAHTZEE_BONUS_VALUE
+            rule_set.yahtzee_bonus_value
         } else {
             0
         };
+        // the bonus is only awarded on the scoring that first takes us over the threshold
+        let upper_section_score = self.capped_upper_section_score.score();
         let upper_section_bonus = if category.is_upper_section()
-            && self.capped_upper_section_score.score() + category_score
-                >= CappedUpperSectionScore::CAP
+            && upper_section_score < rule_set.upper_section_bonus_threshold
+            && upper_section_score + category_score >= rule_set.upper_section_bonus_threshold
         {
-            UPPER_SECTION_BONUS_VALUE
+            rule_set.upper_section_bonus_value
         } else {
             0
         };
@@ -81,13 +120,45 @@ impl ScorecardState {
             .all(|&v| v != ScoreCategoryState::Unscored)
     }
 
-    fn is_joker(&self, roll_counts: &RollCounts) -> bool {
-        if let Some(yahtzee_category) = roll_counts.is_yahtzee()
-            && self.score_category_state()[yahtzee_category] != ScoreCategoryState::Unscored
+    /// Whether this state can be reached from the empty scorecard. Only the capped upper section
+    /// score is constrained, as it must be made up of scores from the filled upper section
+    /// categories.
+    ///
+    /// * `dice` - The dice rolled, as more dice can score more in each category.
+    pub fn is_reachable(&self, dice: &Dice) -> bool {
+        let mut upper_section_mask = 0usize;
+        for (i, score_category) in ScoreCategory::iter()
+            .filter(|x| x.is_upper_section())
+            .enumerate()
         {
-            true
+            if self.score_category_state()[score_category] != ScoreCategoryState::Unscored {
+                upper_section_mask |= 1 << i;
+            }
+        }
+        let reachability =
+            &UPPER_SECTION_REACHABILITY[dice.num_dice() as usize][upper_section_mask];
+        let score = self.capped_upper_section_score.score() as usize;
+        // a score at the cap stands for every score at or above it.
+        if score < self.capped_upper_section_score.cap() as usize {
+            reachability[score]
         } else {
-            false
+            reachability[score..].iter().any(|&x| x)
+        }
+    }
+
+    fn is_joker(&self, roll_counts: &RollCounts, joker_rule: JokerRule) -> bool {
+        let Some(yahtzee_category) = roll_counts.is_yahtzee() else {
+            return false;
+        };
+        if self.score_category_state()[yahtzee_category] == ScoreCategoryState::Unscored {
+            return false;
+        }
+        match joker_rule {
+            JokerRule::FreeChoice => true,
+            JokerRule::Forced | JokerRule::Original => {
+                self.score_category_state()[ScoreCategory::Yahtzee] != ScoreCategoryState::Unscored
+            }
+            JokerRule::None => false,
         }
     }
 }
@@ -96,27 +167,16 @@ impl RollCounts {
     /// Returns None if not a yahtzee, or the corresponding upper-section score category if a
     /// yahtzee.
     pub fn is_yahtzee(&self) -> Option<ScoreCategory> {
-        if !self
+        let face = self
             .roll_counts()
             .iter()
-            .any(|&x| x as usize == RollCounts::NUM_DICE)
-        {
-            return None;
-        }
-        const VAL: u8 = RollCounts::NUM_DICE as u8;
-        match &self.roll_counts() {
-            [VAL, 0, 0, 0, 0, 0] => Some(ScoreCategory::Aces),
-            [0, VAL, 0, 0, 0, 0] => Some(ScoreCategory::Twos),
-            [0, 0, VAL, 0, 0, 0] => Some(ScoreCategory::Threes),
-            [0, 0, 0, VAL, 0, 0] => Some(ScoreCategory::Fours),
-            [0, 0, 0, 0, VAL, 0] => Some(ScoreCategory::Fives),
-            [0, 0, 0, 0, 0, VAL] => Some(ScoreCategory::Sixes),
-            _ => unreachable!(),
-        }
+            .position(|&x| x == self.dice().num_dice())?;
+        // the upper section categories come first, in order of face.
+        ScoreCategory::iter().nth(face)
     }
 
-    /// Returns the value of scoring this roll as the input category.
-    pub fn score_value(&self, category: ScoreCategory, is_joker: bool) -> u8 {
+    /// Returns the value of scoring this roll as the input category under the rules.
+    pub fn score_value(&self, category: ScoreCategory, is_joker: bool, rule_set: &RuleSet) -> u8 {
         use ScoreCategory::*;
 
         let roll_counts = self.roll_counts();
@@ -129,57 +189,103 @@ impl RollCounts {
             Fives => roll_counts[4] * 5,
             Sixes => roll_counts[5] * 6,
             FullHouse => {
-                if is_joker
-                    || (roll_counts.iter().any(|&x| x == 3) && roll_counts.iter().any(|&x| x == 2))
-                {
-                    25
-                } else {
-                    0
+                let full_house = self.full_house();
+                match rule_set.variant {
+                    Variant::Yahtzee if is_joker || full_house.is_some() => {
+                        rule_set.full_house_value
+                    }
+                    Variant::Yatzy | Variant::Yacht => {
+                        full_house.map_or(0, |(triple, pair)| 3 * triple + 2 * pair)
+                    }
+                    _ => 0,
                 }
             }
-            ThreeOfAKind => {
-                if roll_counts.iter().any(|&x| x >= 3) {
-                    self.sum()
-                } else {
-                    0
+            ThreeOfAKind => match rule_set.variant {
+                Variant::Yahtzee if self.highest_of_a_kind(3).is_some() => self.sum(),
+                Variant::Yatzy => self.highest_of_a_kind(3).map_or(0, |face| 3 * face),
+                _ => 0,
+            },
+            FourOfAKind => match rule_set.variant {
+                Variant::Yahtzee if self.highest_of_a_kind(4).is_some() => self.sum(),
+                Variant::Yatzy | Variant::Yacht => {
+                    self.highest_of_a_kind(4).map_or(0, |face| 4 * face)
                 }
-            }
-            FourOfAKind => {
-                if roll_counts.iter().any(|&x| x >= 4) {
-                    self.sum()
-                } else {
-                    0
-                }
-            }
+                _ => 0,
+            },
             SmallStraight => {
-                if is_joker || self.straight_length() >= 4 {
-                    30
+                let is_straight = match rule_set.variant {
+                    Variant::Yahtzee => is_joker || self.straight_length() >= 4,
+                    // only 1-2-3-4-5 counts.
+                    Variant::Yatzy | Variant::Yacht => roll_counts[..5].iter().all(|&x| x >= 1),
+                };
+                if is_straight {
+                    rule_set.small_straight_value
                 } else {
                     0
                 }
             }
             LargeStraight => {
-                if is_joker || self.straight_length() >= 5 {
-                    40
+                let is_straight = match rule_set.variant {
+                    Variant::Yahtzee => is_joker || self.straight_length() >= 5,
+                    // only 2-3-4-5-6 counts.
+                    Variant::Yatzy | Variant::Yacht => roll_counts[1..].iter().all(|&x| x >= 1),
+                };
+                if is_straight {
+                    rule_set.large_straight_value
                 } else {
                     0
                 }
             }
             Yahtzee => {
-                if let Some(_) = self.is_yahtzee() {
-                    50
+                if self.is_yahtzee().is_some() {
+                    rule_set.yahtzee_value
                 } else {
                     0
                 }
             }
             Chance => self.sum(),
+            OnePair => self.highest_of_a_kind(2).map_or(0, |face| 2 * face),
+            TwoPairs => {
+                // two different pairs, so four of a kind is only one pair.
+                let mut pairs = (1u8..=Self::MAX_FACES as u8)
+                    .rev()
+                    .filter(|&face| roll_counts[face as usize - 1] >= 2);
+                match (pairs.next(), pairs.next()) {
+                    (Some(high), Some(low)) => 2 * (high + low),
+                    _ => 0,
+                }
+            }
         }
     }
 
+    /// The (triple, pair) faces of the highest scoring full house, if any. The two faces must
+    /// differ, and with more than five dice the other dice are left out.
+    fn full_house(&self) -> Option<(u8, u8)> {
+        let roll_counts = self.roll_counts();
+        let faces = 1u8..=Self::MAX_FACES as u8;
+        faces
+            .clone()
+            .filter(|&triple| roll_counts[triple as usize - 1] >= 3)
+            .flat_map(|triple| {
+                faces
+                    .clone()
+                    .filter(move |&pair| pair != triple && roll_counts[pair as usize - 1] >= 2)
+                    .map(move |pair| (triple, pair))
+            })
+            .max_by_key(|&(triple, pair)| 3 * triple + 2 * pair)
+    }
+
+    /// The highest face with at least `n` dice showing it, if any.
+    fn highest_of_a_kind(&self, n: u8) -> Option<u8> {
+        (1u8..=Self::MAX_FACES as u8)
+            .rev()
+            .find(|&face| self.roll_counts()[face as usize - 1] >= n)
+    }
+
     fn sum(&self) -> u8 {
         self.roll_counts()
             .iter()
-            .zip(1u8..=Self::NUM_FACES as u8)
+            .zip(1u8..=Self::MAX_FACES as u8)
             .map(|(&x, y)| x * y)
             .sum()
     }
@@ -190,6 +296,8 @@ impl RollCounts {
         for &count in self.roll_counts() {
             if count > 0 {
                 cur += 1;
+            } else {
+                cur = 0;
             }
             max = std::cmp::max(cur, max);
         }
@@ -197,9 +305,35 @@ impl RollCounts {
     }
 }
 
+/// Each upper section category can be scored for face * n, where n is on [0..=num_dice], so we
+/// build up the reachable scores one category at a time.
+fn precompute_upper_section_reachability(
+    num_dice: u8,
+) -> [[bool; MAX_UPPER_SECTION_SCORE + 1]; 1 << UPPER_SECTION_CATEGORIES] {
+    let mut reachability = [[false; MAX_UPPER_SECTION_SCORE + 1]; 1 << UPPER_SECTION_CATEGORIES];
+    reachability[0][0] = true;
+    for mask in 1usize..(1 << UPPER_SECTION_CATEGORIES) {
+        // the lowest filled category, and the mask with it removed (which is already computed).
+        let face = mask.trailing_zeros() as u8 + 1;
+        let prev_mask = mask & (mask - 1);
+        for prev_score in 0..=MAX_UPPER_SECTION_SCORE {
+            if !reachability[prev_mask][prev_score] {
+                continue;
+            }
+            for n in 0..=num_dice as usize {
+                reachability[mask][prev_score + face as usize * n] = true;
+            }
+        }
+    }
+    reachability
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::all_filled_except;
+    use crate::types::{CappedUpperSectionScore, JokerRule};
+    use enum_map::{Enum, EnumMap};
 
     #[test]
     fn valid_categories_free_choice_joker_rule() {
@@ -207,8 +341,8 @@ mod tests {
             .score(ScoreCategory::Aces, 3)
             .unwrap();
         let roll = RollCounts::try_from([5, 0, 0, 0, 0, 0]).unwrap();
-        let joker_rule = JokerRule::FreeChoice;
-        let valid_categories = scorecard_state.valid_score_categories(&roll, joker_rule);
+        let rule_set = &RuleSet::standard(JokerRule::FreeChoice);
+        let valid_categories = scorecard_state.valid_score_categories(&roll, rule_set);
         let mut valid_categories_map = EnumMap::<ScoreCategory, bool>::default();
         for category in ScoreCategory::iter() {
             valid_categories_map[category] = false;
@@ -218,7 +352,9 @@ mod tests {
         }
         for (k, &v) in &valid_categories_map {
             match k {
-                ScoreCategory::Aces => assert!(!v, "Expected {:#?} to be false.", k),
+                ScoreCategory::Aces | ScoreCategory::OnePair | ScoreCategory::TwoPairs => {
+                    assert!(!v, "Expected {:#?} to be false.", k)
+                }
                 _ => assert!(v, "Expected {:#?} to be true.", k),
             };
         }
@@ -230,8 +366,8 @@ mod tests {
             .score(ScoreCategory::Yahtzee, 50)
             .unwrap();
         let roll = RollCounts::try_from([5, 0, 0, 0, 0, 0]).unwrap();
-        let joker_rule = JokerRule::Forced;
-        let valid_categories = scorecard_state.valid_score_categories(&roll, joker_rule);
+        let rule_set = &RuleSet::standard(JokerRule::Forced);
+        let valid_categories = scorecard_state.valid_score_categories(&roll, rule_set);
         let mut valid_categories_map = EnumMap::<ScoreCategory, bool>::default();
         for category in ScoreCategory::iter() {
             valid_categories_map[category] = false;
@@ -247,6 +383,169 @@ mod tests {
         }
     }
 
+    /// Fills every category in `filled` with 0, except the yahtzee box which is scored 50.
+    fn filled(filled: &[ScoreCategory]) -> ScorecardState {
+        let mut scorecard_state = ScorecardState::default();
+        for &score_category in filled {
+            let score = if score_category == ScoreCategory::Yahtzee {
+                50
+            } else {
+                0
+            };
+            scorecard_state = scorecard_state.score(score_category, score).unwrap();
+        }
+        scorecard_state
+    }
+
+    #[test]
+    fn forced_joker_yahtzee_box_open() {
+        use ScoreCategory::*;
+        let scorecard_state = filled(&[Aces]);
+        let roll = RollCounts::try_from([5, 0, 0, 0, 0, 0]).unwrap();
+        let valid_categories =
+            scorecard_state.valid_score_categories(&roll, &RuleSet::standard(JokerRule::Forced));
+        assert_eq!(
+            valid_categories,
+            scorecard_state.valid_non_yahtzee_score_categories()
+        );
+        assert!(valid_categories.contains(&Yahtzee));
+        // no joker scoring until the yahtzee box is filled.
+        assert_eq!(
+            scorecard_state.score_value(&roll, FullHouse, &RuleSet::standard(JokerRule::Forced)),
+            Ok((0, 0))
+        );
+        assert_eq!(
+            scorecard_state.score_value(&roll, Yahtzee, &RuleSet::standard(JokerRule::Forced)),
+            Ok((50, 0))
+        );
+    }
+
+    #[test]
+    fn forced_joker_lower_section_before_upper() {
+        use ScoreCategory::*;
+        let scorecard_state = filled(&[Yahtzee, Aces, FullHouse]);
+        let roll = RollCounts::try_from([5, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(
+            scorecard_state.valid_score_categories(&roll, &RuleSet::standard(JokerRule::Forced)),
+            vec![
+                ThreeOfAKind,
+                FourOfAKind,
+                SmallStraight,
+                LargeStraight,
+                Chance
+            ]
+        );
+        assert_eq!(
+            scorecard_state.score_value(&roll, Twos, &RuleSet::standard(JokerRule::Forced)),
+            Err(ScoringError::InvalidScoreCategory { category: Twos })
+        );
+        assert_eq!(
+            scorecard_state.score_value(
+                &roll,
+                LargeStraight,
+                &RuleSet::standard(JokerRule::Forced)
+            ),
+            Ok((40, 100))
+        );
+        assert_eq!(
+            scorecard_state.score_value(&roll, ThreeOfAKind, &RuleSet::standard(JokerRule::Forced)),
+            Ok((5, 100))
+        );
+    }
+
+    #[test]
+    fn forced_joker_upper_section_for_zero() {
+        use ScoreCategory::*;
+        let rule_set = RuleSet::standard(JokerRule::Forced);
+        let scorecard_state =
+            all_filled_except(&rule_set, &[Aces, Twos, Threes, Fours, Fives, Yahtzee])
+                .score(Yahtzee, 50)
+                .unwrap();
+        let roll = RollCounts::try_from([0, 0, 0, 0, 0, 5]).unwrap();
+        assert_eq!(
+            scorecard_state.valid_score_categories(&roll, &rule_set),
+            vec![Aces, Twos, Threes, Fours, Fives]
+        );
+        // a yahtzee scored 50 earns the bonus even when the joker scores 0.
+        assert_eq!(
+            scorecard_state.score_value(&roll, Twos, &rule_set),
+            Ok((0, 100))
+        );
+    }
+
+    #[test]
+    fn forced_joker_scratched_yahtzee() {
+        use ScoreCategory::*;
+        let scorecard_state = filled(&[Threes]).score(Yahtzee, 0).unwrap();
+        let roll = RollCounts::try_from([0, 0, 5, 0, 0, 0]).unwrap();
+        let valid_categories =
+            scorecard_state.valid_score_categories(&roll, &RuleSet::standard(JokerRule::Forced));
+        assert!(valid_categories.iter().all(|x| !x.is_upper_section()));
+        assert_eq!(
+            scorecard_state.score_value(
+                &roll,
+                SmallStraight,
+                &RuleSet::standard(JokerRule::Forced)
+            ),
+            Ok((30, 0))
+        );
+    }
+
+    #[test]
+    fn original_joker() {
+        use ScoreCategory::*;
+        let roll = RollCounts::try_from([0, 0, 0, 5, 0, 0]).unwrap();
+        let scorecard_state = filled(&[Yahtzee]);
+        assert_eq!(
+            scorecard_state.valid_score_categories(&roll, &RuleSet::standard(JokerRule::Original)),
+            vec![Fours]
+        );
+        let scorecard_state = filled(&[Yahtzee, Fours]);
+        assert_eq!(
+            scorecard_state.valid_score_categories(&roll, &RuleSet::standard(JokerRule::Original)),
+            scorecard_state.valid_non_yahtzee_score_categories()
+        );
+        assert_eq!(
+            scorecard_state.score_value(&roll, Aces, &RuleSet::standard(JokerRule::Original)),
+            Ok((0, 100))
+        );
+        assert_eq!(
+            scorecard_state.score_value(&roll, FullHouse, &RuleSet::standard(JokerRule::Original)),
+            Ok((25, 100))
+        );
+        // with the yahtzee box open, any box may be used, without joker scoring.
+        let scorecard_state = filled(&[Fours]);
+        assert_eq!(
+            scorecard_state.valid_score_categories(&roll, &RuleSet::standard(JokerRule::Original)),
+            scorecard_state.valid_non_yahtzee_score_categories()
+        );
+        assert_eq!(
+            scorecard_state.score_value(&roll, FullHouse, &RuleSet::standard(JokerRule::Original)),
+            Ok((0, 0))
+        );
+    }
+
+    #[test]
+    fn free_choice_joker_unchanged() {
+        use ScoreCategory::*;
+        let roll = RollCounts::try_from([0, 0, 0, 5, 0, 0]).unwrap();
+        let scorecard_state = filled(&[Fours]);
+        assert_eq!(
+            scorecard_state.score_value(
+                &roll,
+                FullHouse,
+                &RuleSet::standard(JokerRule::FreeChoice)
+            ),
+            Ok((25, 0))
+        );
+        let scorecard_state = filled(&[Yahtzee]);
+        assert_eq!(
+            scorecard_state
+                .valid_score_categories(&roll, &RuleSet::standard(JokerRule::FreeChoice)),
+            scorecard_state.valid_non_yahtzee_score_categories()
+        );
+    }
+
     #[test]
     fn test_yahtzee_is_yahtzee() {
         let roll_counts = RollCounts::try_from([0, 5, 0, 0, 0, 0]).unwrap();
@@ -263,7 +562,7 @@ mod tests {
     fn test_upper_category_score() {
         let roll_counts = RollCounts::try_from([2, 2, 1, 0, 0, 0]).unwrap();
         let expected = 4;
-        let score = roll_counts.score_value(ScoreCategory::Twos, false);
+        let score = roll_counts.score_value(ScoreCategory::Twos, false, &RuleSet::default());
         assert_eq!(expected, score);
     }
 
@@ -271,7 +570,7 @@ mod tests {
     fn test_yahtzee_score() {
         let roll_counts = RollCounts::try_from([0, 0, 5, 0, 0, 0]).unwrap();
         let expected = 50;
-        let score = roll_counts.score_value(ScoreCategory::Yahtzee, false);
+        let score = roll_counts.score_value(ScoreCategory::Yahtzee, false, &RuleSet::default());
         assert_eq!(expected, score);
     }
 
@@ -279,7 +578,7 @@ mod tests {
     fn test_not_yahtzee_score() {
         let roll_counts = RollCounts::try_from([0, 0, 4, 0, 1, 0]).unwrap();
         let expected = 0;
-        let score = roll_counts.score_value(ScoreCategory::Yahtzee, false);
+        let score = roll_counts.score_value(ScoreCategory::Yahtzee, false, &RuleSet::default());
         assert_eq!(expected, score);
     }
 
@@ -287,15 +586,216 @@ mod tests {
     fn test_small_straight_score() {
         let roll_counts = RollCounts::try_from([0, 2, 1, 1, 1, 0]).unwrap();
         let expected = 30;
-        let score = roll_counts.score_value(ScoreCategory::SmallStraight, false);
+        let score =
+            roll_counts.score_value(ScoreCategory::SmallStraight, false, &RuleSet::default());
         assert_eq!(expected, score);
     }
 
+    #[test]
+    fn test_gapped_straight_score() {
+        let roll_counts = RollCounts::try_from([1, 1, 0, 1, 1, 1]).unwrap();
+        let expected = 0;
+        let score =
+            roll_counts.score_value(ScoreCategory::LargeStraight, false, &RuleSet::default());
+        assert_eq!(expected, score);
+    }
+
+    #[test]
+    fn upper_section_bonus_awarded_once() {
+        let scorecard_state = ScorecardState::default()
+            .score(ScoreCategory::Sixes, 30)
+            .unwrap()
+            .score(ScoreCategory::Fives, 25)
+            .unwrap();
+        let roll = RollCounts::try_from([0, 0, 0, 5, 0, 0]).unwrap();
+        let first = scorecard_state.score_value(
+            &roll,
+            ScoreCategory::Fours,
+            &RuleSet::standard(JokerRule::FreeChoice),
+        );
+        assert_eq!(first, Ok((20, 35)));
+        let scorecard_state = scorecard_state.score(ScoreCategory::Fours, 20).unwrap();
+        let roll = RollCounts::try_from([1, 1, 1, 1, 1, 0]).unwrap();
+        let second = scorecard_state.score_value(
+            &roll,
+            ScoreCategory::Threes,
+            &RuleSet::standard(JokerRule::FreeChoice),
+        );
+        assert_eq!(second, Ok((3, 0)));
+    }
+
+    #[test]
+    fn upper_section_bonus_above_standard_threshold() {
+        let rule_set = RuleSet {
+            dice: Dice::MAXI,
+            upper_section_bonus_value: 100,
+            upper_section_bonus_threshold: 84,
+            ..RuleSet::yatzy()
+        };
+        let scorecard_state = ScorecardState::new_game(&rule_set)
+            .score(ScoreCategory::Sixes, 36)
+            .unwrap()
+            .score(ScoreCategory::Fives, 30)
+            .unwrap();
+        // 66 would already be past the standard cap of 63.
+        assert_eq!(scorecard_state.capped_upper_section_score.score(), 66);
+        let roll = RollCounts::new([0, 0, 0, 4, 0, 2], Dice::MAXI).unwrap();
+        assert_eq!(
+            scorecard_state.score_value(&roll, ScoreCategory::Fours, &rule_set),
+            Ok((16, 0))
+        );
+        let roll = RollCounts::new([0, 0, 0, 5, 0, 1], Dice::MAXI).unwrap();
+        assert_eq!(
+            scorecard_state.score_value(&roll, ScoreCategory::Fours, &rule_set),
+            Ok((20, 100))
+        );
+    }
+
+    #[test]
+    fn house_rule_values() {
+        let rule_set = RuleSet {
+            full_house_value: 30,
+            yahtzee_bonus_value: 50,
+            upper_section_bonus_value: 50,
+            upper_section_bonus_threshold: 60,
+            ..RuleSet::standard(JokerRule::FreeChoice)
+        };
+        let full_house = RollCounts::try_from([0, 2, 3, 0, 0, 0]).unwrap();
+        assert_eq!(
+            full_house.score_value(ScoreCategory::FullHouse, false, &rule_set),
+            30
+        );
+        let scorecard_state = ScorecardState::default()
+            .score(ScoreCategory::Sixes, 30)
+            .unwrap()
+            .score(ScoreCategory::Fives, 25)
+            .unwrap()
+            .score(ScoreCategory::Yahtzee, 50)
+            .unwrap();
+        let roll = RollCounts::try_from([0, 0, 0, 0, 5, 0]).unwrap();
+        assert_eq!(
+            scorecard_state.score_value(&roll, ScoreCategory::Fours, &rule_set),
+            Ok((0, 50))
+        );
+        let roll = RollCounts::try_from([0, 0, 1, 0, 4, 0]).unwrap();
+        assert_eq!(
+            scorecard_state.score_value(&roll, ScoreCategory::Fours, &rule_set),
+            Ok((0, 0))
+        );
+        let roll = RollCounts::try_from([0, 0, 0, 5, 0, 0]).unwrap();
+        assert_eq!(
+            scorecard_state.score_value(&roll, ScoreCategory::Fours, &rule_set),
+            Ok((20, 100))
+        );
+    }
+
+    #[test]
+    fn yatzy_scores() {
+        use ScoreCategory::*;
+        let rule_set = RuleSet::yatzy();
+        let score = |raw_roll_counts, category| {
+            RollCounts::try_from(raw_roll_counts)
+                .unwrap()
+                .score_value(category, false, &rule_set)
+        };
+        assert_eq!(score([0, 2, 0, 1, 0, 2], OnePair), 12);
+        assert_eq!(score([0, 2, 0, 1, 0, 2], TwoPairs), 16);
+        assert_eq!(score([0, 0, 0, 4, 0, 1], TwoPairs), 0);
+        assert_eq!(score([1, 0, 3, 0, 0, 1], TwoPairs), 0);
+        assert_eq!(score([0, 1, 0, 0, 0, 4], ThreeOfAKind), 18);
+        assert_eq!(score([0, 1, 0, 0, 0, 4], FourOfAKind), 24);
+        assert_eq!(score([0, 1, 1, 0, 0, 3], FourOfAKind), 0);
+        assert_eq!(score([0, 2, 3, 0, 0, 0], FullHouse), 13);
+        assert_eq!(score([0, 5, 0, 0, 0, 0], FullHouse), 0);
+        assert_eq!(score([1, 1, 1, 1, 1, 0], SmallStraight), 15);
+        assert_eq!(score([0, 1, 1, 1, 1, 1], SmallStraight), 0);
+        assert_eq!(score([0, 1, 1, 1, 1, 1], LargeStraight), 20);
+        assert_eq!(score([1, 1, 1, 1, 1, 0], LargeStraight), 0);
+        assert_eq!(score([0, 0, 0, 0, 0, 5], Yahtzee), 50);
+    }
+
+    #[test]
+    fn yacht_scores() {
+        use ScoreCategory::*;
+        let rule_set = RuleSet::yacht();
+        let score = |raw_roll_counts, category| {
+            RollCounts::try_from(raw_roll_counts)
+                .unwrap()
+                .score_value(category, false, &rule_set)
+        };
+        // only the four matching dice count.
+        assert_eq!(score([0, 1, 0, 0, 0, 4], FourOfAKind), 24);
+        assert_eq!(score([0, 0, 0, 0, 0, 5], FourOfAKind), 24);
+        assert_eq!(score([0, 1, 1, 0, 0, 3], FourOfAKind), 0);
+        assert_eq!(score([0, 2, 3, 0, 0, 0], FullHouse), 13);
+        assert_eq!(score([1, 1, 1, 1, 1, 0], SmallStraight), 30);
+        assert_eq!(score([0, 1, 1, 1, 1, 1], SmallStraight), 0);
+        assert_eq!(score([0, 1, 1, 1, 1, 1], LargeStraight), 30);
+        assert_eq!(score([1, 1, 1, 1, 0, 1], LargeStraight), 0);
+        assert_eq!(score([0, 0, 0, 0, 0, 5], Yahtzee), 50);
+        let scorecard_state = ScorecardState::new_game(&rule_set);
+        let roll = RollCounts::try_from([0, 0, 0, 0, 0, 5]).unwrap();
+        let valid_categories = scorecard_state.valid_score_categories(&roll, &rule_set);
+        assert_eq!(valid_categories.len(), ScoreCategory::LENGTH - 3);
+        assert!(!valid_categories.contains(&ThreeOfAKind));
+        // no upper section bonus, and no joker or bonus yacht.
+        let scorecard_state = scorecard_state
+            .score(Yahtzee, 50)
+            .unwrap()
+            .score(Fives, 25)
+            .unwrap()
+            .score(Fours, 20)
+            .unwrap();
+        assert_eq!(
+            scorecard_state.score_value(&roll, Sixes, &rule_set),
+            Ok((30, 0))
+        );
+        assert_eq!(
+            scorecard_state.score_value(&roll, SmallStraight, &rule_set),
+            Ok((0, 0))
+        );
+    }
+
+    #[test]
+    fn yatzy_has_no_joker_or_bonus() {
+        let rule_set = RuleSet::yatzy();
+        let scorecard_state = ScorecardState::new_game(&rule_set)
+            .score(ScoreCategory::Yahtzee, 50)
+            .unwrap()
+            .score(ScoreCategory::Sixes, 24)
+            .unwrap();
+        let roll = RollCounts::try_from([0, 0, 0, 0, 0, 5]).unwrap();
+        let valid_categories = scorecard_state.valid_score_categories(&roll, &rule_set);
+        assert_eq!(valid_categories.len(), ScoreCategory::LENGTH - 2);
+        assert_eq!(
+            scorecard_state.score_value(&roll, ScoreCategory::FullHouse, &rule_set),
+            Ok((0, 0))
+        );
+        assert_eq!(
+            scorecard_state.score_value(&roll, ScoreCategory::OnePair, &rule_set),
+            Ok((12, 0))
+        );
+        // pairs are not part of yahtzee.
+        let scorecard_state = ScorecardState::default();
+        let standard = RuleSet::default();
+        assert!(
+            !scorecard_state
+                .valid_score_categories(&roll, &standard)
+                .contains(&ScoreCategory::OnePair)
+        );
+        assert_eq!(
+            scorecard_state.score_value(&roll, ScoreCategory::TwoPairs, &standard),
+            Err(ScoringError::InvalidScoreCategory {
+                category: ScoreCategory::TwoPairs
+            })
+        );
+    }
+
     #[test]
     fn test_yahtzee_scored_as_full_house_no_joker() {
         let roll_counts = RollCounts::try_from([0, 5, 0, 0, 0, 0]).unwrap();
         let expected = 0;
-        let score = roll_counts.score_value(ScoreCategory::FullHouse, false);
+        let score = roll_counts.score_value(ScoreCategory::FullHouse, false, &RuleSet::default());
         assert_eq!(expected, score);
     }
 
@@ -303,14 +803,153 @@ mod tests {
     fn test_yahtzee_scored_as_full_house_yes_joker() {
         let roll_counts = RollCounts::try_from([0, 5, 0, 0, 0, 0]).unwrap();
         let expected = 25;
-        let score = roll_counts.score_value(ScoreCategory::FullHouse, true);
+        let score = roll_counts.score_value(ScoreCategory::FullHouse, true, &RuleSet::default());
         assert_eq!(expected, score);
     }
 
+    #[test]
+    fn maxi_yahtzee_and_straights() {
+        let roll_counts = RollCounts::new([0, 0, 0, 6, 0, 0], Dice::MAXI).unwrap();
+        assert_eq!(roll_counts.is_yahtzee(), Some(ScoreCategory::Fours));
+        let roll_counts = RollCounts::new([0, 0, 0, 5, 1, 0], Dice::MAXI).unwrap();
+        assert_eq!(roll_counts.is_yahtzee(), None);
+        // a straight may have a die to spare.
+        let roll_counts = RollCounts::new([2, 1, 1, 1, 1, 0], Dice::MAXI).unwrap();
+        let rule_set = RuleSet {
+            dice: Dice::MAXI,
+            ..RuleSet::yatzy()
+        };
+        assert_eq!(
+            roll_counts.score_value(ScoreCategory::SmallStraight, false, &rule_set),
+            15
+        );
+        assert_eq!(
+            roll_counts.score_value(ScoreCategory::LargeStraight, false, &rule_set),
+            0
+        );
+    }
+
+    #[test]
+    fn maxi_full_house() {
+        let yatzy = RuleSet {
+            dice: Dice::MAXI,
+            ..RuleSet::yatzy()
+        };
+        let yahtzee = RuleSet {
+            dice: Dice::MAXI,
+            ..RuleSet::standard(JokerRule::FreeChoice)
+        };
+        let score = |raw_roll_counts, rule_set: &RuleSet| {
+            RollCounts::new(raw_roll_counts, Dice::MAXI)
+                .unwrap()
+                .score_value(ScoreCategory::FullHouse, false, rule_set)
+        };
+        // 3+3 is the higher face as the triple and the lower as the pair.
+        assert_eq!(score([0, 0, 0, 0, 3, 3], &yatzy), 3 * 6 + 2 * 5);
+        assert_eq!(score([0, 0, 0, 0, 3, 3], &yahtzee), 25);
+        // 4+2 leaves out a die of the four.
+        assert_eq!(score([0, 2, 0, 4, 0, 0], &yatzy), 3 * 4 + 2 * 2);
+        assert_eq!(score([0, 2, 0, 4, 0, 0], &yahtzee), 25);
+        // 3+2+1 leaves out the odd die.
+        assert_eq!(score([1, 0, 0, 0, 2, 3], &yatzy), 3 * 6 + 2 * 5);
+        assert_eq!(score([1, 0, 0, 0, 2, 3], &yahtzee), 25);
+        assert_eq!(score([0, 0, 0, 0, 0, 6], &yatzy), 0);
+        assert_eq!(score([1, 1, 0, 0, 1, 3], &yatzy), 0);
+    }
+
+    #[test]
+    fn upper_section_score_without_aces_unreachable() {
+        let scorecard_state = ScorecardState::new(
+            Variant::Yahtzee,
+            CappedUpperSectionScore::new(1, CappedUpperSectionScore::STANDARD_CAP).unwrap(),
+            EnumMap::default(),
+        );
+        assert!(!scorecard_state.is_reachable(&Dice::STANDARD));
+        let scorecard_state = ScorecardState::default()
+            .score(ScoreCategory::Aces, 1)
+            .unwrap();
+        assert!(scorecard_state.is_reachable(&Dice::STANDARD));
+    }
+
+    #[test]
+    fn upper_section_score_sixes_only() {
+        let mut score_category_state = EnumMap::default();
+        score_category_state[ScoreCategory::Sixes] = ScoreCategoryState::Scored;
+        for score in 0..=CappedUpperSectionScore::STANDARD_CAP {
+            let scorecard_state = ScorecardState::new(
+                Variant::Yahtzee,
+                CappedUpperSectionScore::new(score, CappedUpperSectionScore::STANDARD_CAP).unwrap(),
+                score_category_state,
+            );
+            assert_eq!(
+                scorecard_state.is_reachable(&Dice::STANDARD),
+                score % 6 == 0 && score <= 30,
+                "Unexpected reachability for upper section score {score}."
+            );
+            // six sixes are worth 36.
+            assert_eq!(
+                scorecard_state.is_reachable(&Dice::MAXI),
+                score % 6 == 0 && score <= 36,
+                "Unexpected Maxi reachability for upper section score {score}."
+            );
+        }
+    }
+
+    #[test]
+    fn upper_section_cap_reachable() {
+        // three of each face makes exactly 63.
+        let mut scorecard_state = ScorecardState::default();
+        for score_category in ScoreCategory::iter().filter(|x| x.is_upper_section()) {
+            let face = score_category.into_usize() as u8 + 1;
+            scorecard_state = scorecard_state.score(score_category, face * 3).unwrap();
+        }
+        assert_eq!(
+            scorecard_state.capped_upper_section_score.score(),
+            CappedUpperSectionScore::STANDARD_CAP
+        );
+        assert!(scorecard_state.is_reachable(&Dice::STANDARD));
+    }
+
+    #[test]
+    fn upper_section_cap_reachable_above_standard() {
+        // five sixes make 30, so sixes alone can only reach a cap of 30 or below.
+        let mut score_category_state = EnumMap::default();
+        score_category_state[ScoreCategory::Sixes] = ScoreCategoryState::Scored;
+        let at_cap = |cap| {
+            ScorecardState::new(
+                Variant::Yahtzee,
+                CappedUpperSectionScore::new(cap, cap).unwrap(),
+                score_category_state,
+            )
+        };
+        assert!(at_cap(30).is_reachable(&Dice::STANDARD));
+        assert!(at_cap(24).is_reachable(&Dice::STANDARD));
+        assert!(!at_cap(31).is_reachable(&Dice::STANDARD));
+        // the Maxi Yatzy bonus threshold of 84 needs more than five dice.
+        let mut scorecard_state = ScorecardState::new(
+            Variant::Yahtzee,
+            CappedUpperSectionScore::new(0, 84).unwrap(),
+            EnumMap::default(),
+        );
+        for score_category in ScoreCategory::iter().filter(|x| x.is_upper_section()) {
+            let face = score_category.into_usize() as u8 + 1;
+            scorecard_state = scorecard_state.score(score_category, face * 4).unwrap();
+        }
+        assert_eq!(scorecard_state.capped_upper_section_score.score(), 84);
+        assert!(scorecard_state.is_reachable(&Dice::MAXI));
+        assert!(scorecard_state.is_reachable(&Dice::STANDARD));
+        let scorecard_state = ScorecardState::new(
+            Variant::Yahtzee,
+            CappedUpperSectionScore::new(84, 84).unwrap(),
+            score_category_state,
+        );
+        assert!(!scorecard_state.is_reachable(&Dice::MAXI));
+    }
+
     #[test]
     fn test_terminal() {
         let mut scorecard_state = ScorecardState::default();
-        for score_category in ScoreCategory::iter() {
+        for score_category in scorecard_state.valid_non_yahtzee_score_categories() {
             scorecard_state = scorecard_state.score(score_category, 0).unwrap();
         }
         let is_terminal = scorecard_state.is_terminal();